@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rgpxsee::gpx::parse_track;
+
+// Any input must produce a track or an error, never a panic.
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_track(std::io::Cursor::new(data));
+});
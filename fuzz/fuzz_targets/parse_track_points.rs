@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rgpxsee::gpx::parse_track_points;
+
+// Any input must produce points or an error, never a panic.
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_track_points(std::io::Cursor::new(data));
+});
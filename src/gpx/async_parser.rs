@@ -0,0 +1,65 @@
+use quick_xml::{Reader, events::Event};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::gpx::{
+    Error, ParseOptions, Track,
+    err::InternalError,
+    trkpt::{TrackParser, UTF8_BOM},
+};
+
+/// Async counterpart of [`parse_track`](crate::gpx::parse_track) for readers
+/// that must not block the runtime, e.g. an upload body.
+pub async fn parse_track_async<R: AsyncBufRead + Unpin>(reader: R) -> Result<Track, Error> {
+    parse_track_with_options_async(reader, ParseOptions::default()).await
+}
+
+/// Async counterpart of
+/// [`parse_track_with_options`](crate::gpx::parse_track_with_options).
+pub async fn parse_track_with_options_async<R: AsyncBufRead + Unpin>(
+    mut reader: R,
+    opts: ParseOptions,
+) -> Result<Track, Error> {
+    if reader
+        .fill_buf()
+        .await
+        .map_err(InternalError::from)?
+        .starts_with(UTF8_BOM)
+    {
+        reader.consume(UTF8_BOM.len());
+    }
+
+    let mut xml = Reader::from_reader(reader);
+    xml.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut parser = TrackParser::new(opts);
+
+    loop {
+        match xml
+            .read_event_into_async(&mut buf)
+            .await
+            .map_err(|e| InternalError::from(e).at(xml.buffer_position()))?
+        {
+            Event::Eof => break,
+            event => parser.handle(event)?,
+        }
+        buf.clear();
+    }
+
+    Ok(parser.finish())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn parse_track_async_matches_sync() {
+    let file = tokio::fs::File::open("assert/Alt_Portsmouth.gpx")
+        .await
+        .unwrap();
+    let track = parse_track_async(tokio::io::BufReader::new(file))
+        .await
+        .unwrap();
+
+    let sync = Track::from_path("assert/Alt_Portsmouth.gpx").unwrap();
+    assert_eq!(track, sync);
+    assert_eq!(track.total_point_count(), 1219);
+}
@@ -0,0 +1,58 @@
+/// A latitude/longitude rectangle, edges included. Boxes crossing the
+/// antimeridian are not supported: `min_lon` must not exceed `max_lon`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        BoundingBox {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+        }
+    }
+
+    /// Whether `(lat, lon)` lies inside the box or on its edge.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat) && (self.min_lon..=self.max_lon).contains(&lon)
+    }
+
+    /// The `(lat, lon)` midpoint of the box.
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min_lat + self.max_lat) / 2.0,
+            (self.min_lon + self.max_lon) / 2.0,
+        )
+    }
+
+    /// The smallest box holding every `(lat, lon)`, or `None` if there are
+    /// none.
+    pub(crate) fn enclosing(coords: impl IntoIterator<Item = (f64, f64)>) -> Option<Self> {
+        coords.into_iter().fold(None, |bbox, (lat, lon)| {
+            let b = bbox.unwrap_or(BoundingBox::new(lat, lon, lat, lon));
+            Some(BoundingBox::new(
+                b.min_lat.min(lat),
+                b.min_lon.min(lon),
+                b.max_lat.max(lat),
+                b.max_lon.max(lon),
+            ))
+        })
+    }
+}
+
+#[test]
+fn bounding_box_contains_its_edges() {
+    let bbox = BoundingBox::new(50.0, -1.1, 50.1, -1.0);
+
+    assert!(bbox.contains(50.05, -1.05));
+    assert!(bbox.contains(50.0, -1.0));
+    assert!(!bbox.contains(50.1000001, -1.05));
+    assert!(!bbox.contains(50.05, -0.99));
+}
@@ -0,0 +1,124 @@
+use crate::gpx::{TrackPoint, segment::haversine_m};
+
+/// How a single point changed between two versions of a track. Indices refer
+/// to the flattened point sequence of each track (see
+/// [`Track::points`](crate::gpx::Track::points)).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointChange {
+    Unchanged {
+        old: usize,
+        new: usize,
+    },
+    Moved {
+        old: usize,
+        new: usize,
+        distance_m: f64,
+    },
+    Added {
+        new: usize,
+    },
+    Removed {
+        old: usize,
+    },
+}
+
+/// Result of [`Track::diff`](crate::gpx::Track::diff), in sequence order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackDiff {
+    pub changes: Vec<PointChange>,
+}
+
+impl TrackDiff {
+    /// Returns `true` if every point is [`PointChange::Unchanged`].
+    pub fn is_unchanged(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|c| matches!(c, PointChange::Unchanged { .. }))
+    }
+}
+
+/// Aligns `old` against `new` with a longest-common-subsequence pass where
+/// two points match when they are within `tolerance_m` of each other. Runs in
+/// `O(old.len() * new.len())` time and memory.
+pub(crate) fn align(old: &[&TrackPoint], new: &[&TrackPoint], tolerance_m: f64) -> TrackDiff {
+    let (n, m) = (old.len(), new.len());
+    let close = |i: usize, j: usize| haversine_m(old[i], new[j]) <= tolerance_m;
+
+    // lcs[i][j] is the best alignment length of old[i..] and new[j..].
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if close(i, j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if close(i, j) {
+            let (a, b) = (old[i], new[j]);
+            changes.push(if a.lat == b.lat && a.lon == b.lon {
+                PointChange::Unchanged { old: i, new: j }
+            } else {
+                PointChange::Moved {
+                    old: i,
+                    new: j,
+                    distance_m: haversine_m(a, b),
+                }
+            });
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            changes.push(PointChange::Removed { old: i });
+            i += 1;
+        } else {
+            changes.push(PointChange::Added { new: j });
+            j += 1;
+        }
+    }
+    changes.extend((i..n).map(|old| PointChange::Removed { old }));
+    changes.extend((j..m).map(|new| PointChange::Added { new }));
+
+    TrackDiff { changes }
+}
+
+#[test]
+fn diff_reports_added_and_moved_points() {
+    let parse = |gpx: &str| crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let old = parse(
+        r#"<gpx><trk><trkseg>
+            <trkpt lat="0.0" lon="0.0"></trkpt>
+            <trkpt lat="0.0" lon="0.001"></trkpt>
+            <trkpt lat="0.0" lon="0.002"></trkpt>
+        </trkseg></trk></gpx>"#,
+    );
+    let new = parse(
+        r#"<gpx><trk><trkseg>
+            <trkpt lat="0.0" lon="0.0"></trkpt>
+            <trkpt lat="0.0" lon="0.001"></trkpt>
+            <trkpt lat="0.0005" lon="0.0015"></trkpt>
+            <trkpt lat="0.00001" lon="0.002"></trkpt>
+        </trkseg></trk></gpx>"#,
+    );
+
+    let diff = old.diff(&new, 5.0);
+
+    assert_eq!(
+        diff.changes[..3],
+        [
+            PointChange::Unchanged { old: 0, new: 0 },
+            PointChange::Unchanged { old: 1, new: 1 },
+            PointChange::Added { new: 2 },
+        ]
+    );
+    assert!(matches!(
+        diff.changes[3],
+        PointChange::Moved { old: 2, new: 3, .. }
+    ));
+    assert!(!diff.is_unchanged());
+    assert!(old.diff(&old, 0.0).is_unchanged());
+}
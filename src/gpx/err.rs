@@ -2,18 +2,47 @@ use quick_xml::events::attributes::AttrError;
 
 #[derive(Debug)]
 pub enum Error {
-    Input,
-    InvalidFormat,
-    InvalidData,
+    Input(std::io::Error),
+    /// The input is not well-formed XML.
+    InvalidFormat {
+        message: String,
+        /// Byte offset into the input where the error was detected.
+        offset: Option<u64>,
+        /// The XML parser's error, also returned by `source()`.
+        source: Box<quick_xml::Error>,
+    },
+    /// The XML is well-formed but a value in it is not valid GPX.
+    InvalidData {
+        message: String,
+    },
+    /// The document contains no track points, see
+    /// [`parse_track_strict`](crate::gpx::parse_track_strict).
+    NoTrackData,
 }
 
 #[derive(Debug)]
 pub enum InternalError {
     Io(std::io::Error),
-    Xml(String),
+    Xml {
+        source: quick_xml::Error,
+        position: Option<usize>,
+    },
     InvalidTrackPoint(String),
 }
 
+impl InternalError {
+    /// Attaches the reader's byte offset to an XML error.
+    pub(crate) fn at(self, position: usize) -> Self {
+        match self {
+            InternalError::Xml { source, .. } => InternalError::Xml {
+                source,
+                position: Some(position),
+            },
+            e => e,
+        }
+    }
+}
+
 impl From<std::io::Error> for InternalError {
     fn from(value: std::io::Error) -> Self {
         InternalError::Io(value)
@@ -22,22 +51,67 @@ impl From<std::io::Error> for InternalError {
 
 impl From<quick_xml::Error> for InternalError {
     fn from(value: quick_xml::Error) -> Self {
-        InternalError::Xml(value.to_string())
+        match value {
+            // A failing underlying reader (e.g. a corrupt gzip stream) is an
+            // input problem, not a malformed document.
+            quick_xml::Error::Io(e) => InternalError::Io(
+                std::sync::Arc::try_unwrap(e)
+                    .unwrap_or_else(|e| std::io::Error::new(e.kind(), e.to_string())),
+            ),
+            source => InternalError::Xml {
+                source,
+                position: None,
+            },
+        }
     }
 }
 
 impl From<AttrError> for InternalError {
     fn from(e: AttrError) -> Self {
-        InternalError::Xml(e.to_string())
+        InternalError::Xml {
+            source: quick_xml::Error::InvalidAttr(e),
+            position: None,
+        }
     }
 }
 
 impl From<InternalError> for Error {
     fn from(e: InternalError) -> Self {
         match e {
-            InternalError::Io(_) => Error::Input,
-            InternalError::Xml(_) => Error::InvalidFormat,
-            InternalError::InvalidTrackPoint(_) => Error::InvalidData,
+            InternalError::Io(e) => Error::Input(e),
+            InternalError::Xml { source, position } => Error::InvalidFormat {
+                message: source.to_string(),
+                offset: position.map(|p| p as u64),
+                source: Box::new(source),
+            },
+            InternalError::InvalidTrackPoint(message) => Error::InvalidData { message },
+        }
+    }
+}
+
+impl std::fmt::Display for InternalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InternalError::Io(e) => e.fmt(f),
+            InternalError::Xml {
+                source,
+                position: Some(position),
+            } => write!(f, "{source} at byte {position}"),
+            InternalError::Xml {
+                source,
+                position: None,
+            } => source.fmt(f),
+            InternalError::InvalidTrackPoint(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for InternalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InternalError::Io(e) => Some(e),
+            InternalError::Xml { source, .. } => Some(source),
+            InternalError::InvalidTrackPoint(_) => None,
         }
     }
 }
@@ -45,11 +119,141 @@ impl From<InternalError> for Error {
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::Input => write!(f, "invalid input"),
-            Error::InvalidFormat => write!(f, "invalid GPX format"),
-            Error::InvalidData => write!(f, "invalid GPX data"),
+            Error::Input(_) => write!(f, "invalid input"),
+            Error::InvalidFormat {
+                message,
+                offset: Some(offset),
+                ..
+            } => write!(f, "invalid GPX format at byte {offset}: {message}"),
+            Error::InvalidFormat {
+                message,
+                offset: None,
+                ..
+            } => write!(f, "invalid GPX format: {message}"),
+            Error::InvalidData { message } => write!(f, "invalid GPX data: {message}"),
+            Error::NoTrackData => write!(f, "no track data"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Input(e) => Some(e),
+            Error::InvalidFormat { source, .. } => Some(source.as_ref()),
+            Error::InvalidData { .. } | Error::NoTrackData => None,
         }
     }
 }
 
-impl std::error::Error for Error {}
+/// Serializes like a derived enum, with the I/O and XML causes written as
+/// strings (the latter is `message`). Errors cannot be deserialized, as their
+/// causes cannot be rebuilt.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStructVariant;
+
+        match self {
+            Error::Input(e) => {
+                serializer.serialize_newtype_variant("Error", 0, "Input", &e.to_string())
+            }
+            Error::InvalidFormat {
+                message, offset, ..
+            } => {
+                let mut v = serializer.serialize_struct_variant("Error", 1, "InvalidFormat", 2)?;
+                v.serialize_field("message", message)?;
+                v.serialize_field("offset", offset)?;
+                v.end()
+            }
+            Error::InvalidData { message } => {
+                let mut v = serializer.serialize_struct_variant("Error", 2, "InvalidData", 1)?;
+                v.serialize_field("message", message)?;
+                v.end()
+            }
+            Error::NoTrackData => serializer.serialize_unit_variant("Error", 3, "NoTrackData"),
+        }
+    }
+}
+
+#[test]
+fn error_source_preserves_cause() {
+    use std::error::Error as _;
+
+    let err = crate::gpx::parse_track(std::io::Cursor::new("<gpx><trk></gpx>")).unwrap_err();
+    let Error::InvalidFormat { ref message, .. } = err else {
+        panic!("expected InvalidFormat, got {err:?}");
+    };
+    assert!(message.contains("trk"), "{message}");
+    assert!(err.to_string().ends_with(message.as_str()));
+    let xml = err
+        .source()
+        .and_then(|e| e.downcast_ref::<quick_xml::Error>())
+        .unwrap();
+    assert_eq!(&xml.to_string(), message);
+
+    let err = crate::gpx::Track::from_path("assert/does_not_exist.gpx").unwrap_err();
+    let io = err
+        .source()
+        .and_then(|e| e.downcast_ref::<std::io::Error>())
+        .unwrap();
+    assert_eq!(io.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn invalid_format_reports_position() {
+    let gpx = r#"<gpx><trk><trkseg><trkpt lat="0.0" lon="0.0"></trkseg></trk></gpx>"#;
+
+    let err = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap_err();
+
+    let Error::InvalidFormat {
+        offset: Some(offset),
+        ..
+    } = err
+    else {
+        panic!("expected a positioned format error, got {err:?}");
+    };
+    assert!(offset > 0);
+    assert!(err.to_string().contains(&format!("at byte {offset}")));
+}
+
+#[test]
+fn internal_error_display() {
+    let err = InternalError::InvalidTrackPoint("lat is not a number".into());
+    assert_eq!(err.to_string(), "lat is not a number");
+
+    let err = InternalError::from(std::io::Error::other("disk on fire"));
+    assert_eq!(err.to_string(), "disk on fire");
+
+    let boxed: Box<dyn std::error::Error> =
+        Box::new(InternalError::from(quick_xml::Error::UnexpectedEof("Comment".into())).at(42));
+    assert!(boxed.to_string().ends_with("at byte 42"), "{boxed}");
+    assert!(boxed.source().is_some());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn error_serializes_to_json() {
+    let err = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk><trkseg><trkpt lat="0.0" lon="0.0"></trkseg></trk></gpx>"#,
+    ))
+    .unwrap_err();
+    let Error::InvalidFormat {
+        ref message,
+        offset,
+        ..
+    } = err
+    else {
+        panic!("expected InvalidFormat, got {err:?}");
+    };
+    let json = serde_json::to_value(&err).unwrap();
+    assert_eq!(json["InvalidFormat"]["message"], message.as_str());
+    assert_eq!(json["InvalidFormat"]["offset"], offset.unwrap());
+
+    let err = crate::gpx::Track::from_path("assert/does_not_exist.gpx").unwrap_err();
+    let json = serde_json::to_value(&err).unwrap();
+    assert!(json["Input"].is_string());
+
+    let json = serde_json::to_string(&Error::NoTrackData).unwrap();
+    assert_eq!(json, r#""NoTrackData""#);
+}
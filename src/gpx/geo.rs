@@ -0,0 +1,27 @@
+/// Mean Earth radius in metres, as used by [`haversine_m`].
+pub const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// Great-circle distance in metres between `(lat1, lon1)` and `(lat2, lon2)`
+/// in degrees, on a sphere of radius [`EARTH_RADIUS_M`]. Works on plain
+/// coordinates, e.g. to check whether a track passes near a point of
+/// interest.
+pub fn haversine_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let dlat = (lat2 - lat1).to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+
+    let lat1 = lat1.to_radians();
+    let lat2 = lat2.to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+
+    let c = 2.0 * h.sqrt().atan2((1.0 - h).sqrt());
+    EARTH_RADIUS_M * c
+}
+
+#[test]
+fn haversine_thousandth_of_a_degree_at_equator() {
+    let d = haversine_m(0.0, 0.0, 0.0, 0.001);
+
+    assert!((d - 111.195).abs() < 1e-3, "{d}");
+    assert_eq!(haversine_m(0.0, 0.0, 0.001, 0.0), d);
+    assert_eq!(haversine_m(51.5, -0.1, 51.5, -0.1), 0.0);
+}
@@ -0,0 +1,130 @@
+use serde_json::{Value, json};
+
+use crate::gpx::{Segment, Track, TrackPoint, Waypoint};
+
+/// Converts `track` to a GeoJSON `FeatureCollection`. Each segment becomes a
+/// `LineString` feature carrying `distance_m`, `ascent_m` and `descent_m`
+/// properties, followed by one `Point` feature per waypoint.
+pub fn track_to_geojson(track: &Track) -> String {
+    let features: Vec<Value> = track
+        .segments()
+        .iter()
+        .map(segment_feature)
+        .chain(track.waypoints().iter().map(waypoint_feature))
+        .collect();
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+    .to_string()
+}
+
+/// Converts a single segment to a GeoJSON `LineString` feature.
+pub fn segment_to_geojson(segment: &Segment) -> String {
+    segment_feature(segment).to_string()
+}
+
+/// Converts a waypoint to a GeoJSON `Point` feature.
+pub fn waypoint_to_geojson(wp: &Waypoint) -> String {
+    waypoint_feature(wp).to_string()
+}
+
+fn segment_feature(segment: &Segment) -> Value {
+    let (ascent_m, descent_m) = segment.total_ascent_descent_m();
+    let coordinates: Vec<Value> = segment.points().iter().map(position).collect();
+
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "LineString",
+            "coordinates": coordinates,
+        },
+        "properties": {
+            "distance_m": segment.total_distance_m(),
+            "ascent_m": ascent_m,
+            "descent_m": descent_m,
+        },
+    })
+}
+
+fn waypoint_feature(wp: &Waypoint) -> Value {
+    let coordinates = match wp.ele {
+        Some(ele) => json!([wp.lon, wp.lat, ele]),
+        None => json!([wp.lon, wp.lat]),
+    };
+
+    json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "Point",
+            "coordinates": coordinates,
+        },
+        "properties": {
+            "name": wp.name,
+            "desc": wp.desc,
+            "sym": wp.sym.as_ref().map(|s| s.as_str()),
+            "time": wp.time,
+        },
+    })
+}
+
+/// GeoJSON positions are `[lon, lat]`, with elevation appended when known.
+fn position(pt: &TrackPoint) -> Value {
+    match pt.ele {
+        Some(ele) => json!([pt.lon, pt.lat, ele]),
+        None => json!([pt.lon, pt.lat]),
+    }
+}
+
+#[test]
+fn track_to_geojson_feature_collection() {
+    let gpx = r#"
+    <gpx>
+      <wpt lat="1.0" lon="2.0"><name>Start</name></wpt>
+      <trk>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.0"><ele>100</ele></trkpt>
+          <trkpt lat="0.0" lon="0.001"><ele>110</ele></trkpt>
+          <trkpt lat="0.0" lon="0.002"></trkpt>
+        </trkseg>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.003"></trkpt>
+          <trkpt lat="0.0" lon="0.004"></trkpt>
+        </trkseg>
+      </trk>
+    </gpx>
+    "#;
+    let track = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+
+    let v: Value = serde_json::from_str(&track_to_geojson(&track)).unwrap();
+
+    assert_eq!(v["type"], "FeatureCollection");
+    let features = v["features"].as_array().unwrap();
+    assert_eq!(features.len(), 3);
+    assert_eq!(features[0]["geometry"]["type"], "LineString");
+    assert_eq!(
+        features[0]["geometry"]["coordinates"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(
+        features[0]["geometry"]["coordinates"][1],
+        json!([0.001, 0.0, 110.0])
+    );
+    assert_eq!(features[0]["properties"]["ascent_m"], 10.0);
+    assert_eq!(
+        features[1]["geometry"]["coordinates"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let wp: Value = serde_json::from_str(&waypoint_to_geojson(&track.waypoints()[0])).unwrap();
+    assert_eq!(wp["geometry"]["type"], "Point");
+    assert_eq!(wp["geometry"]["coordinates"], json!([2.0, 1.0]));
+    assert_eq!(wp["properties"]["name"], "Start");
+}
@@ -0,0 +1,73 @@
+use std::io::Write;
+
+use quick_xml::{
+    Writer,
+    events::{BytesDecl, BytesEnd, BytesStart, Event},
+};
+
+use crate::gpx::{Error, Segment, Track, err::InternalError, writer::write_text};
+
+const KML_NS: &str = "http://www.opengis.net/kml/2.2";
+
+/// Serializes `track` as a KML 2.2 document with one `<Placemark>` holding a
+/// `<LineString>` per segment. Elevations are written when present. KML
+/// line strings need two points, so shorter segments are left out.
+pub fn write_kml<W: Write>(track: &Track, writer: W) -> Result<(), Error> {
+    write_document(track, &mut Writer::new_with_indent(writer, b' ', 2)).map_err(Error::from)
+}
+
+fn write_document<W: Write>(track: &Track, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    xml.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    let mut root = BytesStart::new("kml");
+    root.push_attribute(("xmlns", KML_NS));
+    xml.write_event(Event::Start(root))?;
+    xml.write_event(Event::Start(BytesStart::new("Document")))?;
+
+    for seg in track.segments().iter().filter(|s| s.point_count() >= 2) {
+        xml.write_event(Event::Start(BytesStart::new("Placemark")))?;
+        xml.write_event(Event::Start(BytesStart::new("LineString")))?;
+        write_text(xml, "coordinates", &coordinates(seg))?;
+        xml.write_event(Event::End(BytesEnd::new("LineString")))?;
+        xml.write_event(Event::End(BytesEnd::new("Placemark")))?;
+    }
+
+    xml.write_event(Event::End(BytesEnd::new("Document")))?;
+    xml.write_event(Event::End(BytesEnd::new("kml")))?;
+    xml.get_mut().flush()?;
+    Ok(())
+}
+
+/// KML's `lon,lat[,alt]` tuples, separated by spaces.
+fn coordinates(seg: &Segment) -> String {
+    seg.points()
+        .iter()
+        .map(|pt| match pt.ele {
+            Some(ele) => format!("{},{},{}", pt.lon, pt.lat, ele),
+            None => format!("{},{}", pt.lon, pt.lat),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn write_kml_line_string_per_segment() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="50.8127" lon="-1.0865"><ele>8.6</ele></trkpt>
+              <trkpt lat="50.81318" lon="-1.08571"></trkpt>
+            </trkseg>
+            <trkseg><trkpt lat="50.81461" lon="-1.08377"></trkpt></trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let mut out = Vec::new();
+    write_kml(&track, &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+
+    assert!(text.contains(r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#));
+    assert_eq!(text.matches("<LineString>").count(), 1);
+    assert!(text.contains("<coordinates>-1.0865,50.8127,8.6 -1.08571,50.81318</coordinates>"));
+}
@@ -0,0 +1,174 @@
+use quick_xml::events::BytesStart;
+
+use crate::gpx::{err::InternalError, trkpt::TextHandler};
+
+/// The `<metadata>` block of a GPX 1.1 file, describing the file as a
+/// whole. GPX 1.0 files have no such block.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub desc: Option<String>,
+    /// The `<name>` of the `<author>`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub author: Option<String>,
+    /// The `href` of the first `<link>`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub link: Option<String>,
+    /// When the file was created.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub time: Option<String>,
+}
+
+type MetaApplyfn = fn(&mut Metadata, &str) -> Result<(), InternalError>;
+
+fn apply_name(meta: &mut Metadata, s: &str) -> Result<(), InternalError> {
+    meta.name = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_desc(meta: &mut Metadata, s: &str) -> Result<(), InternalError> {
+    meta.desc = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_time(meta: &mut Metadata, s: &str) -> Result<(), InternalError> {
+    meta.time = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_author(meta: &mut Metadata, s: &str) -> Result<(), InternalError> {
+    meta.author = Some(s.to_string());
+    Ok(())
+}
+
+/// Direct children of `<metadata>`.
+const HANDLERS: &[TextHandler<Metadata>] = &[
+    TextHandler {
+        tag: b"name",
+        apply: apply_name,
+    },
+    TextHandler {
+        tag: b"desc",
+        apply: apply_desc,
+    },
+    TextHandler {
+        tag: b"time",
+        apply: apply_time,
+    },
+];
+
+/// Children of `<metadata><author>`.
+const AUTHOR_HANDLERS: &[TextHandler<Metadata>] = &[TextHandler {
+    tag: b"name",
+    apply: apply_author,
+}];
+
+/// Accumulates the `<metadata>` block currently being parsed. Nested
+/// elements such as `<author><name>` and `<copyright>` are told apart from
+/// the direct children by tracking the element path below `<metadata>`.
+#[derive(Default)]
+pub(crate) struct MetadataBuilder {
+    metadata: Metadata,
+    depth: usize,
+    in_author: bool,
+    target: Option<MetaApplyfn>,
+}
+
+impl MetadataBuilder {
+    pub(crate) fn start(&mut self, e: &BytesStart) -> Result<(), InternalError> {
+        self.depth += 1;
+        let name = e.local_name();
+        let handlers = match (self.depth, self.in_author) {
+            (1, _) if name.as_ref() == b"author" => {
+                self.in_author = true;
+                &[][..]
+            }
+            (1, _) => HANDLERS,
+            (2, true) => AUTHOR_HANDLERS,
+            _ => &[][..],
+        };
+        self.target = handlers
+            .iter()
+            .find(|h| h.tag == name.as_ref())
+            .map(|h| h.apply);
+        if self.depth == 1 && name.as_ref() == b"link" {
+            self.link(e)?;
+        }
+        Ok(())
+    }
+
+    /// A self-closing child, e.g. `<link href="..."/>`.
+    pub(crate) fn empty(&mut self, e: &BytesStart) -> Result<(), InternalError> {
+        if self.depth == 0 && e.local_name().as_ref() == b"link" {
+            self.link(e)?;
+        }
+        Ok(())
+    }
+
+    fn link(&mut self, e: &BytesStart) -> Result<(), InternalError> {
+        if self.metadata.link.is_some() {
+            return Ok(());
+        }
+        for attr in e.attributes() {
+            let attr = attr?;
+            if attr.key.as_ref() == b"href" {
+                self.metadata.link = Some(attr.unescape_value()?.into_owned());
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn wants_text(&self) -> bool {
+        self.target.is_some()
+    }
+
+    pub(crate) fn text(&mut self, s: &str) -> Result<(), InternalError> {
+        match self.target {
+            Some(apply) => apply(&mut self.metadata, s),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn end(&mut self) {
+        if self.depth == 1 {
+            self.in_author = false;
+        }
+        self.depth = self.depth.saturating_sub(1);
+        self.target = None;
+    }
+
+    pub(crate) fn finish(self) -> Metadata {
+        self.metadata
+    }
+}
+
+#[test]
+fn parse_full_metadata() {
+    let track = crate::gpx::Track::from_path("assert/Full_Metadata.gpx").unwrap();
+
+    let meta = track.metadata().unwrap();
+    assert_eq!(meta.name.as_deref(), Some("Portsea Loop"));
+    assert_eq!(
+        meta.desc.as_deref(),
+        Some("Morning loop around Portsea Island")
+    );
+    assert_eq!(meta.author.as_deref(), Some("Jo Bloggs"));
+    assert_eq!(
+        meta.link.as_deref(),
+        Some("https://example.com/routes/portsea")
+    );
+    assert_eq!(meta.time.as_deref(), Some("2024-05-04T08:55:00Z"));
+
+    let pt = &track[0][0];
+    assert_eq!(pt.time.as_deref(), Some("2024-05-04T09:00:00Z"));
+}
+
+#[test]
+fn parse_without_metadata() {
+    let track = crate::gpx::Track::from_path("assert/Portsea_GPX10.gpx").unwrap();
+
+    assert_eq!(track.metadata(), None);
+}
@@ -1,12 +1,46 @@
+#[cfg(feature = "tokio")]
+mod async_parser;
+mod bbox;
+mod diff;
 mod err;
+pub mod geo;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+pub mod kml;
+mod metadata;
+mod options;
+pub mod polyline;
+pub mod render;
 mod segment;
+mod stats;
+pub mod tcx;
 mod track;
 mod trkpt;
+mod wpt;
+mod writer;
 
+#[cfg(feature = "tokio")]
+pub use self::async_parser::{parse_track_async, parse_track_with_options_async};
+pub use self::bbox::BoundingBox;
+pub use self::diff::{PointChange, TrackDiff};
 pub use self::err::Error;
-pub use self::segment::Segment;
+pub use self::metadata::Metadata;
+pub use self::options::{ElevationSource, NormalizeOptions, ParseOptions};
+pub use self::segment::{DistanceFormula, ElevationSample, GradeSample, PaceSample, Segment};
+pub use self::stats::TrackStats;
+#[cfg(feature = "rayon")]
+pub use self::stats::compute_stats_batch;
 pub use self::track::Track;
-pub use self::trkpt::TrackPoint;
+pub use self::trkpt::{TrackPoint, TrackPointBuilder, TrackPointStream};
+pub use self::wpt::{Waypoint, WaypointKind};
 
 pub use trkpt::parse_track;
+pub use trkpt::parse_track_bytes;
+#[cfg(feature = "flate2")]
+pub use trkpt::parse_track_gz;
+pub use trkpt::parse_track_lenient;
 pub use trkpt::parse_track_points;
+pub use trkpt::parse_track_str;
+pub use trkpt::parse_track_strict;
+pub use trkpt::parse_track_with_options;
+pub use writer::write_track;
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+/// A place a track point's elevation can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElevationSource {
+    /// The standard `<ele>` child of `<trkpt>`.
+    Gpx,
+    /// A vendor `<ele>` or `<altitude>` element inside `<extensions>`, as
+    /// written by devices with a barometric altimeter.
+    Extension,
+}
+
+/// Options controlling how [`parse_track_with_options`](crate::gpx::parse_track_with_options)
+/// builds a [`Track`](crate::gpx::Track).
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Segments with fewer points than this are merged into the previous
+    /// segment. A short segment with no previous segment to merge into is
+    /// dropped. Defaults to 1, which keeps every non-empty segment.
+    pub min_segment_points: usize,
+    /// Elevation sources in order of preference. The first source present on
+    /// a point is stored in `ele`; sources not listed are ignored. Defaults
+    /// to `<ele>` with the extension elevation as a fallback.
+    pub elevation_priority: Vec<ElevationSource>,
+    /// Treat a `<trkpt>` without any elevation as invalid. Defaults to
+    /// `false`.
+    pub strict_elevation: bool,
+    /// Drop invalid `<trkpt>`s (bad coordinates, unparsable values or, with
+    /// `strict_elevation`, no elevation) instead of failing the whole
+    /// parse. Malformed XML is still an error. Defaults to `false`.
+    pub skip_invalid_points: bool,
+    /// Fail once the document has more than this many track points, to
+    /// bound memory use on untrusted input. Defaults to no limit.
+    pub max_points: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            min_segment_points: 1,
+            elevation_priority: vec![ElevationSource::Gpx, ElevationSource::Extension],
+            strict_elevation: false,
+            skip_invalid_points: false,
+            max_points: None,
+        }
+    }
+}
+
+/// Cleanup steps applied by [`Track::normalize`](crate::gpx::Track::normalize).
+/// Each step runs only when its field is `Some`; the default does nothing.
+#[derive(Debug, Clone, Default)]
+pub struct NormalizeOptions {
+    /// Drop consecutive points closer than this many metres.
+    pub dedup_threshold_m: Option<f64>,
+    /// Drop points with a leg to either neighbour faster than this speed.
+    pub max_speed_kmh: Option<f64>,
+    /// Smooth elevation with a moving average over this many points.
+    pub smooth_window: Option<usize>,
+    /// Split segments where consecutive points are further apart in time.
+    pub split_gap: Option<Duration>,
+}
@@ -0,0 +1,216 @@
+use crate::gpx::{Error, TrackPoint, err::InternalError, trkpt::check_lat_lon};
+
+/// Google's precision: coordinates are stored as integers in units of
+/// 1e-5 degrees. OSRM and Valhalla also use 6 ("polyline6").
+pub const DEFAULT_PRECISION: u32 = 5;
+
+/// The largest supported precision. Beyond it the scaled coordinates no
+/// longer fit the format's 64-bit values.
+pub const MAX_PRECISION: u32 = 10;
+
+/// Encodes the coordinates of `points` with Google's [Encoded Polyline
+/// Algorithm][algo] at [`DEFAULT_PRECISION`]. Elevation, time and the other
+/// fields are not part of the format and are dropped.
+///
+/// [algo]: https://developers.google.com/maps/documentation/utilities/polylinealgorithm
+pub fn encode(points: &[TrackPoint]) -> String {
+    encode_points(points, DEFAULT_PRECISION)
+}
+
+/// Like [`encode`], with coordinates rounded to `precision` decimal places.
+///
+/// # Panics
+///
+/// If `precision` is above [`MAX_PRECISION`].
+pub fn encode_with_precision(points: &[TrackPoint], precision: u32) -> String {
+    encode_points(points, precision)
+}
+
+pub(crate) fn encode_points<'a>(
+    points: impl IntoIterator<Item = &'a TrackPoint>,
+    precision: u32,
+) -> String {
+    assert!(
+        precision <= MAX_PRECISION,
+        "polyline precision {precision} is above {MAX_PRECISION}"
+    );
+    let factor = 10f64.powi(precision as i32);
+    let mut out = String::new();
+    let (mut prev_lat, mut prev_lon) = (0i64, 0i64);
+    for pt in points {
+        let lat = (pt.lat * factor).round() as i64;
+        let lon = (pt.lon * factor).round() as i64;
+        encode_value(lat - prev_lat, &mut out);
+        encode_value(lon - prev_lon, &mut out);
+        (prev_lat, prev_lon) = (lat, lon);
+    }
+    out
+}
+
+/// Decodes an encoded polyline into points carrying only `lat` and `lon`.
+pub fn decode(s: &str) -> Result<Vec<TrackPoint>, Error> {
+    decode_with_precision(s, DEFAULT_PRECISION)
+}
+
+/// Like [`decode`], for a polyline encoded with `precision` decimal places.
+/// Decoding with the wrong precision usually fails the lat/lon range check.
+/// Precisions above [`MAX_PRECISION`] are rejected.
+pub fn decode_with_precision(s: &str, precision: u32) -> Result<Vec<TrackPoint>, Error> {
+    if precision > MAX_PRECISION {
+        return Err(Error::InvalidData {
+            message: format!("polyline precision must be at most {MAX_PRECISION}."),
+        });
+    }
+    let factor = 10f64.powi(precision as i32);
+    let mut bytes = s.bytes();
+    let mut points = Vec::new();
+    let (mut lat, mut lon) = (0i64, 0i64);
+
+    while let Some(dlat) = decode_value(&mut bytes)? {
+        let dlon = decode_value(&mut bytes)?.ok_or_else(|| {
+            InternalError::InvalidTrackPoint("polyline ends after a latitude.".into())
+        })?;
+        (lat, lon) = lat
+            .checked_add(dlat)
+            .zip(lon.checked_add(dlon))
+            .ok_or_else(out_of_range)?;
+        let (lat, lon) = check_lat_lon(
+            Some(lat as f64 / factor),
+            Some(lon as f64 / factor),
+            "polyline",
+        )?;
+        points.push(TrackPoint {
+            lat,
+            lon,
+            ..Default::default()
+        });
+    }
+    Ok(points)
+}
+
+fn encode_value(value: i64, out: &mut String) {
+    let mut v = if value < 0 { !(value << 1) } else { value << 1 } as u64;
+    while v >= 0x20 {
+        out.push(char::from((0x20 | (v & 0x1f)) as u8 + 63));
+        v >>= 5;
+    }
+    out.push(char::from(v as u8 + 63));
+}
+
+/// Reads one value, or `None` at the end of the input.
+fn decode_value(bytes: &mut impl Iterator<Item = u8>) -> Result<Option<i64>, InternalError> {
+    let mut result = 0u64;
+    let mut shift = 0;
+    loop {
+        let Some(b) = bytes.next() else {
+            return match shift {
+                0 => Ok(None),
+                _ => Err(InternalError::InvalidTrackPoint(
+                    "polyline ends mid-value.".into(),
+                )),
+            };
+        };
+        if !(63..=126).contains(&b) {
+            return Err(InternalError::InvalidTrackPoint(format!(
+                "invalid polyline character {:?}.",
+                char::from(b)
+            )));
+        }
+        let chunk = u64::from(b - 63);
+        let bits = chunk & 0x1f;
+        // The last chunk that fits only has room for 4 of its 5 bits.
+        if shift >= 64 || (shift > 59 && bits >> (64 - shift) != 0) {
+            return Err(out_of_range());
+        }
+        result |= bits << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            break;
+        }
+    }
+    let value = (result >> 1) as i64;
+    Ok(Some(if result & 1 == 1 { !value } else { value }))
+}
+
+fn out_of_range() -> InternalError {
+    InternalError::InvalidTrackPoint("polyline value out of range.".into())
+}
+
+#[test]
+fn polyline_matches_google_reference() {
+    let points = vec![
+        TrackPoint::builder(38.5, -120.2).build(),
+        TrackPoint::builder(40.7, -120.95).ele(12.0).build(),
+        TrackPoint::builder(43.252, -126.453).build(),
+    ];
+
+    let encoded = encode(&points);
+    assert_eq!(encoded, "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+
+    let decoded = decode(&encoded).unwrap();
+    assert_eq!(decoded.len(), 3);
+    for (a, b) in decoded.iter().zip(&points) {
+        assert!((a.lat - b.lat).abs() < 1e-9);
+        assert!((a.lon - b.lon).abs() < 1e-9);
+        assert_eq!(a.ele, None);
+    }
+
+    assert!(decode("").unwrap().is_empty());
+    assert!(matches!(decode("_p~iF"), Err(Error::InvalidData { .. })));
+    assert!(matches!(decode("_p~i"), Err(Error::InvalidData { .. })));
+    assert!(matches!(
+        decode("_p~iF~ps| "),
+        Err(Error::InvalidData { .. })
+    ));
+    // A delta close to i64::MAX must not overflow the running total.
+    let Err(Error::InvalidData { message }) = decode(&format!("_p~iF~ps|U}}{}N??", "~".repeat(11)))
+    else {
+        panic!("expected an out-of-range error");
+    };
+    assert_eq!(message, "polyline value out of range.");
+    // Nor may a value carry bits past 64.
+    for input in [
+        format!("{}O", "~".repeat(12)),
+        format!("{}_?", "~".repeat(12)),
+    ] {
+        let Err(Error::InvalidData { message }) = decode(&input) else {
+            panic!("expected an out-of-range error for {input}");
+        };
+        assert_eq!(message, "polyline value out of range.");
+    }
+}
+
+#[test]
+fn polyline6_matches_reference() {
+    let points = vec![
+        TrackPoint::builder(38.5, -120.2).build(),
+        TrackPoint::builder(40.7, -120.95).build(),
+        TrackPoint::builder(43.252, -126.453).build(),
+    ];
+
+    let encoded = encode_with_precision(&points, 6);
+    assert_eq!(encoded, "_izlhA~rlgdF_{geC~ywl@_kwzCn`{nI");
+
+    let decoded = decode_with_precision(&encoded, 6).unwrap();
+    for (a, b) in decoded.iter().zip(&points) {
+        assert!((a.lat - b.lat).abs() < 1e-9);
+        assert!((a.lon - b.lon).abs() < 1e-9);
+    }
+    assert!(decode(&encoded).is_err());
+    assert!(matches!(
+        decode_with_precision("_p~iF~ps|U", 400),
+        Err(Error::InvalidData { .. })
+    ));
+    assert_eq!(
+        decode_with_precision(&encode_with_precision(&points, 10), 10)
+            .unwrap()
+            .len(),
+        3
+    );
+}
+
+#[test]
+#[should_panic(expected = "polyline precision 11 is above 10")]
+fn polyline_encode_rejects_large_precision() {
+    encode_with_precision(&[TrackPoint::builder(38.5, -120.2).build()], 11);
+}
@@ -0,0 +1,212 @@
+use std::fmt::Write as _;
+
+use crate::gpx::Track;
+
+/// Space around the plot, in pixels, for the axis labels.
+const PAD: f64 = 40.0;
+/// Space around the track on the map, in pixels.
+const MAP_MARGIN: f64 = 10.0;
+
+/// Renders the track's elevation profile (see [`Track::elevation_profile`])
+/// as a standalone SVG document of `width` x `height` pixels. Distance runs
+/// along the x axis and elevation up the y axis, both scaled to fit, with
+/// `<text>` labels for the minimum and maximum elevation and the total
+/// distance. A track without elevation data yields an empty canvas.
+pub fn elevation_profile_svg(track: &Track, width: u32, height: u32) -> String {
+    let (w, h) = (f64::from(width), f64::from(height));
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+
+    let profile = track.elevation_profile();
+    if let (Some(first), Some(last)) = (profile.first(), profile.last()) {
+        let (min_ele, max_ele) = profile
+            .iter()
+            .fold((first.elevation_m, first.elevation_m), |(lo, hi), s| {
+                (lo.min(s.elevation_m), hi.max(s.elevation_m))
+            });
+        let (plot_w, plot_h) = ((w - 2.0 * PAD).max(1.0), (h - 2.0 * PAD).max(1.0));
+        let x = |d: f64| match last.distance_m {
+            0.0 => PAD,
+            total => PAD + d / total * plot_w,
+        };
+        let y = |e: f64| match max_ele - min_ele {
+            0.0 => PAD + plot_h / 2.0,
+            span => PAD + (max_ele - e) / span * plot_h,
+        };
+
+        let points: Vec<String> = profile
+            .iter()
+            .map(|s| format!("{:.1},{:.1}", x(s.distance_m), y(s.elevation_m)))
+            .collect();
+        let _ = write!(
+            svg,
+            r#"<polyline fill="none" stroke="black" points="{}"/>"#,
+            points.join(" ")
+        );
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{max_ele:.0} m</text>"#,
+            PAD - 4.0,
+            y(max_ele)
+        );
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{min_ele:.0} m</text>"#,
+            PAD - 4.0,
+            y(min_ele)
+        );
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{:.2} km</text>"#,
+            PAD + plot_w,
+            h - PAD / 4.0,
+            last.distance_m / 1000.0
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Renders a top-down map of the track as a standalone SVG document of
+/// `width` x `height` pixels: one `<polyline>` per segment and a `<circle>`
+/// per waypoint. Coordinates are projected with spherical Web Mercator
+/// (EPSG:3857) and the bounding box is fitted to the canvas, keeping its
+/// aspect ratio.
+pub fn map_svg(track: &Track, width: u32, height: u32) -> String {
+    let (w, h) = (f64::from(width), f64::from(height));
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+
+    let projected = track
+        .points()
+        .map(|p| mercator(p.lat, p.lon))
+        .chain(track.waypoints().iter().map(|w| mercator(w.lat, w.lon)));
+    let bounds = projected.fold(None, |b: Option<(f64, f64, f64, f64)>, (x, y)| {
+        Some(match b {
+            None => (x, y, x, y),
+            Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        })
+    });
+    let Some((min_x, min_y, max_x, max_y)) = bounds else {
+        svg.push_str("</svg>");
+        return svg;
+    };
+
+    let (plot_w, plot_h) = (
+        (w - 2.0 * MAP_MARGIN).max(1.0),
+        (h - 2.0 * MAP_MARGIN).max(1.0),
+    );
+    let (span_x, span_y) = (max_x - min_x, max_y - min_y);
+    let scale = match (span_x > 0.0, span_y > 0.0) {
+        (true, true) => (plot_w / span_x).min(plot_h / span_y),
+        (true, false) => plot_w / span_x,
+        (false, true) => plot_h / span_y,
+        (false, false) => 0.0,
+    };
+    // Centre the scaled box on the canvas; y grows downwards in SVG.
+    let (off_x, off_y) = ((w - span_x * scale) / 2.0, (h - span_y * scale) / 2.0);
+    let pixel = |lat: f64, lon: f64| {
+        let (x, y) = mercator(lat, lon);
+        (off_x + (x - min_x) * scale, off_y + (max_y - y) * scale)
+    };
+
+    for seg in track.segments() {
+        let points: Vec<String> = seg
+            .points()
+            .iter()
+            .map(|p| {
+                let (x, y) = pixel(p.lat, p.lon);
+                format!("{x:.1},{y:.1}")
+            })
+            .collect();
+        let _ = write!(
+            svg,
+            r#"<polyline fill="none" stroke="red" points="{}"/>"#,
+            points.join(" ")
+        );
+    }
+    for wpt in track.waypoints() {
+        let (x, y) = pixel(wpt.lat, wpt.lon);
+        let _ = write!(
+            svg,
+            r#"<circle cx="{x:.1}" cy="{y:.1}" r="3" fill="blue"/>"#
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Spherical Web Mercator on the unit sphere; only ratios matter here.
+fn mercator(lat: f64, lon: f64) -> (f64, f64) {
+    let lat = lat.clamp(-85.051_128_78, 85.051_128_78).to_radians();
+    (
+        lon.to_radians(),
+        (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln(),
+    )
+}
+
+#[test]
+fn elevation_profile_svg_plots_every_sample() {
+    let track = Track::from_path("assert/Alt_Portsmouth.gpx").unwrap();
+    let samples = track.elevation_profile().len();
+
+    let svg = elevation_profile_svg(&track, 800, 200);
+
+    assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+    assert!(svg.contains("<polyline"));
+    let points = svg.split(r#"points=""#).nth(1).unwrap();
+    let points = &points[..points.find('"').unwrap()];
+    assert_eq!(points.split(' ').count(), samples);
+    assert_eq!(svg.matches("<text").count(), 3);
+    assert!(svg.contains(" km</text>"));
+}
+
+#[test]
+fn elevation_profile_svg_single_point() {
+    let track = Track::new(vec![crate::gpx::Segment::new(vec![
+        crate::gpx::TrackPoint::builder(0.0, 0.0).ele(10.0).build(),
+    ])]);
+
+    let svg = elevation_profile_svg(&track, 100, 100);
+
+    assert!(svg.contains(r#"points="40.0,50.0""#));
+    assert!(elevation_profile_svg(&Track::new(Vec::new()), 100, 100).contains("<svg"));
+}
+
+#[test]
+fn map_svg_fits_track_to_canvas() {
+    let track = Track::from_path("assert/Alt_Portsmouth.gpx").unwrap();
+
+    let svg = map_svg(&track, 400, 300);
+
+    assert!(svg.contains(r#"viewBox="0 0 400 300""#));
+    assert_eq!(svg.matches("<polyline").count(), track.segment_count());
+    let points = svg.split(r#"points=""#).nth(1).unwrap();
+    let points = &points[..points.find('"').unwrap()];
+    for pair in points.split(' ') {
+        let (x, y) = pair.split_once(',').unwrap();
+        let (x, y): (f64, f64) = (x.parse().unwrap(), y.parse().unwrap());
+        assert!((0.0..=400.0).contains(&x) && (0.0..=300.0).contains(&y));
+    }
+}
+
+#[test]
+fn map_svg_draws_waypoints() {
+    let track: Track = r#"<gpx>
+        <wpt lat="46.537" lon="7.962"><name>Jungfrau</name></wpt>
+        <trk><trkseg>
+          <trkpt lat="46.5" lon="7.9"></trkpt>
+          <trkpt lat="46.6" lon="8.0"></trkpt>
+        </trkseg></trk>
+    </gpx>"#
+        .parse()
+        .unwrap();
+
+    let svg = map_svg(&track, 200, 200);
+
+    assert_eq!(svg.matches("<circle").count(), 1);
+}
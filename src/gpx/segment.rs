@@ -1,61 +1,952 @@
-use crate::gpx::trkpt;
+use std::{sync::OnceLock, time::Duration};
 
-const EARTH_RADIUS_M: f64 = 6_371_000.0;
+use chrono::{DateTime, FixedOffset, SecondsFormat, TimeDelta};
 
-#[derive(Debug)]
+use crate::gpx::{Error, geo, trkpt};
+
+/// Grade of one leg of a segment, keyed by the cumulative distance at the
+/// end of that leg.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GradeSample {
+    pub distance_m: f64,
+    pub grade_percent: f64,
+}
+
+/// Pace over one leg of a segment, keyed by the cumulative distance at the
+/// end of that leg.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PaceSample {
+    pub distance_m: f64,
+    pub pace_min_per_km: f64,
+}
+
+/// Elevation of a point paired with its cumulative distance from the start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElevationSample {
+    pub distance_m: f64,
+    pub elevation_m: f64,
+}
+
+impl From<ElevationSample> for (f64, f64) {
+    /// Converts to `(distance_m, elevation_m)`, e.g. for a charting library.
+    fn from(s: ElevationSample) -> Self {
+        (s.distance_m, s.elevation_m)
+    }
+}
+
+/// How the distance between two points is measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DistanceFormula {
+    /// Great-circle distance on a sphere. Fast, off by up to ~0.5%.
+    #[default]
+    Haversine,
+    /// Vincenty's inverse formula on the WGS-84 ellipsoid.
+    Vincenty,
+}
+
+/// A run of points. Points are fixed at construction, so the haversine
+/// distance and ascent/descent are computed on first use and cached.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Segment {
     points: Vec<trkpt::TrackPoint>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    distance_m: OnceLock<f64>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ascent_descent_m: OnceLock<(f64, f64)>,
+}
+
+/// Two segments are equal when their points are, whatever has been cached.
+impl PartialEq for Segment {
+    fn eq(&self, other: &Self) -> bool {
+        self.points == other.points
+    }
+}
+
+impl std::ops::Index<usize> for Segment {
+    type Output = trkpt::TrackPoint;
+
+    fn index(&self, index: usize) -> &trkpt::TrackPoint {
+        &self.points[index]
+    }
 }
 
 impl Segment {
     pub fn new(points: Vec<trkpt::TrackPoint>) -> Self {
-        Self { points }
+        Self {
+            points,
+            distance_m: OnceLock::new(),
+            ascent_descent_m: OnceLock::new(),
+        }
     }
 
     pub fn points(&self) -> &[trkpt::TrackPoint] {
         &self.points
     }
 
+    pub fn point_count(&self) -> usize {
+        self.points.len()
+    }
+
+    /// The number of points, matching `segment[i]` indexing.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether the segment has no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&trkpt::TrackPoint> {
+        self.points.first()
+    }
+
+    pub fn last(&self) -> Option<&trkpt::TrackPoint> {
+        self.points.last()
+    }
+
     pub fn total_distance_m(&self) -> f64 {
+        *self.distance_m.get_or_init(|| {
+            self.points
+                .windows(2)
+                .map(|w| haversine_m(&w[0], &w[1]))
+                .sum()
+        })
+    }
+
+    #[cfg(test)]
+    fn is_distance_cached(&self) -> bool {
+        self.distance_m.get().is_some()
+    }
+
+    /// Total distance measured with `formula`. [`Segment::total_distance_m`]
+    /// is the same as passing [`DistanceFormula::Haversine`].
+    pub fn total_distance_with(&self, formula: DistanceFormula) -> f64 {
+        let distance = match formula {
+            DistanceFormula::Haversine => haversine_m,
+            DistanceFormula::Vincenty => vincenty_m,
+        };
+        self.points.windows(2).map(|w| distance(&w[0], &w[1])).sum()
+    }
+
+    /// Like [`Segment::total_distance_m`] but measured on the WGS-84
+    /// ellipsoid with [Vincenty's inverse formula][vincenty], which is
+    /// accurate to well under a millimetre where haversine can be off by
+    /// ~0.5%. Slower, so prefer haversine unless the precision matters.
+    ///
+    /// [vincenty]: https://en.wikipedia.org/wiki/Vincenty%27s_formulae
+    pub fn total_distance_vincenty_m(&self) -> f64 {
+        self.total_distance_with(DistanceFormula::Vincenty)
+    }
+
+    pub fn total_ascent_descent_m(&self) -> (f64, f64) {
+        *self.ascent_descent_m.get_or_init(|| {
+            let mut ascent = 0.0;
+            let mut descent = 0.0;
+
+            for w in self.points.windows(2) {
+                let a = &w[0];
+                let b = &w[1];
+
+                let (Some(e1), Some(e2)) = (a.ele, b.ele) else {
+                    continue;
+                };
+
+                let delta = e2 - e1;
+                if delta > 0.0 {
+                    ascent += delta;
+                } else if delta < 0.0 {
+                    descent += -delta;
+                }
+            }
+            (ascent, descent)
+        })
+    }
+
+    /// Returns the mean heart rate over the points that have a reading.
+    pub fn average_hr(&self) -> Option<f64> {
+        mean(self.points.iter().filter_map(|p| p.hr.map(f64::from)))
+    }
+
+    /// Returns the mean power over the points that have a reading.
+    pub fn average_power(&self) -> Option<f64> {
+        mean(self.points.iter().filter_map(|p| p.power.map(f64::from)))
+    }
+
+    /// Same as [`average_power`](Self::average_power).
+    pub fn avg_power(&self) -> Option<f64> {
+        self.average_power()
+    }
+
+    /// Returns the normalized power: the power readings are resampled to one
+    /// per second (holding the last reading across short gaps), smoothed with
+    /// a 30-second rolling mean, and the fourth root of the mean of the fourth
+    /// powers is taken. Unlike [`average_power`](Self::average_power) this
+    /// weights hard efforts more heavily.
+    ///
+    /// Gaps longer than 30 seconds, such as pauses, split the data into
+    /// separate runs that are smoothed on their own. Returns `None` when no
+    /// run covers 30 seconds.
+    pub fn normalized_power(&self) -> Option<f64> {
+        let readings: Vec<(DateTime<FixedOffset>, f64)> = self
+            .points
+            .iter()
+            .filter_map(|p| Some((p.timestamp()?, f64::from(p.power?))))
+            .collect();
+
+        let mut fourth_powers = Vec::new();
+        let mut run = Vec::new();
+        for w in readings.windows(2) {
+            let secs = (w[1].0 - w[0].0).num_seconds();
+            if (0..=NP_MAX_GAP_SECS).contains(&secs) {
+                run.extend(std::iter::repeat_n(w[0].1, secs as usize));
+            } else {
+                run.push(w[0].1);
+                fourth_powers.extend(rolling_fourth_powers(&run));
+                run.clear();
+            }
+        }
+        if let Some(&(_, last)) = readings.last() {
+            run.push(last);
+            fourth_powers.extend(rolling_fourth_powers(&run));
+        }
+
+        mean(fourth_powers.into_iter()).map(|m| m.powf(0.25))
+    }
+
+    pub fn max_elevation(&self) -> Option<f64> {
+        self.elevation_range().map(|(_, max)| max)
+    }
+
+    pub fn min_elevation(&self) -> Option<f64> {
+        self.elevation_range().map(|(min, _)| min)
+    }
+
+    /// Returns `(min, max)` elevation over the points that have one.
+    pub fn elevation_range(&self) -> Option<(f64, f64)> {
+        self.points
+            .iter()
+            .filter_map(|p| p.ele)
+            .fold(None, |range, e| match range {
+                None => Some((e, e)),
+                Some((min, max)) => Some((f64::min(min, e), f64::max(max, e))),
+            })
+    }
+
+    /// Returns the point `distance_m` metres from the start of the segment,
+    /// linearly interpolating lat, lon and ele within the leg that contains
+    /// it. Time is interpolated when both ends of the leg have timestamps.
+    /// Returns `None` if the distance is negative or beyond the segment.
+    pub fn point_at_distance(&self, distance_m: f64) -> Option<trkpt::TrackPoint> {
+        if distance_m < 0.0 {
+            return None;
+        }
+
+        let mut travelled = 0.0;
+        for w in self.points.windows(2) {
+            let d = haversine_m(&w[0], &w[1]);
+            if travelled + d >= distance_m {
+                let frac = if d > 0.0 {
+                    (distance_m - travelled) / d
+                } else {
+                    0.0
+                };
+                return Some(interpolate(&w[0], &w[1], frac));
+            }
+            travelled += d;
+        }
+
+        match self.points.as_slice() {
+            [pt] if distance_m == 0.0 => Some(pt.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the point at time `at`, interpolated between the two
+    /// timestamped points that bracket it. Points without a timestamp are
+    /// ignored; `None` if `at` is outside the segment's recorded times.
+    pub fn point_at_time(&self, at: DateTime<FixedOffset>) -> Option<trkpt::TrackPoint> {
+        let mut prev: Option<(&trkpt::TrackPoint, DateTime<FixedOffset>)> = None;
+
+        for pt in &self.points {
+            let Some(t) = pt.timestamp() else {
+                continue;
+            };
+            if t == at {
+                return Some(pt.clone());
+            }
+            if let Some((pp, t0)) = prev
+                && t0 < at
+                && at < t
+            {
+                let frac = (at - t0).num_milliseconds() as f64 / (t - t0).num_milliseconds() as f64;
+                return Some(interpolate(pp, pt, frac));
+            }
+            prev = Some((pt, t));
+        }
+        None
+    }
+
+    /// Returns the part of the segment between `start_m` and `end_m` metres
+    /// from its start, with interpolated points (see
+    /// [`Segment::point_at_distance`]) at exactly those distances. The
+    /// result is empty unless `0 <= start_m < end_m <= total_distance_m()`.
+    pub fn crop_by_distance(&self, start_m: f64, end_m: f64) -> Segment {
+        if start_m >= end_m {
+            return Segment::new(Vec::new());
+        }
+        let (Some(first), Some(last)) = (
+            self.point_at_distance(start_m),
+            self.point_at_distance(end_m),
+        ) else {
+            return Segment::new(Vec::new());
+        };
+
+        let mut points = vec![first];
+        let mut travelled = 0.0;
+        for (i, pt) in self.points.iter().enumerate() {
+            if i > 0 {
+                travelled += haversine_m(&self.points[i - 1], pt);
+            }
+            if travelled > start_m && travelled < end_m {
+                points.push(pt.clone());
+            }
+        }
+        points.push(last);
+        Segment::new(points)
+    }
+
+    /// Returns the points timestamped within `start..=end`, with points
+    /// interpolated at exactly `start` and `end` when they fall inside a
+    /// leg. Points without a timestamp are dropped.
+    pub fn crop_by_time(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Segment {
+        let mut points = Vec::new();
+        let mut prev: Option<(&trkpt::TrackPoint, DateTime<FixedOffset>)> = None;
+
+        for pt in &self.points {
+            let Some(t) = pt.timestamp() else {
+                continue;
+            };
+            let frac = |t0: DateTime<FixedOffset>, at: DateTime<FixedOffset>| {
+                (at - t0).num_milliseconds() as f64 / (t - t0).num_milliseconds() as f64
+            };
+            if let Some((pp, t0)) = prev
+                && t0 < start
+                && start < t
+            {
+                points.push(interpolate(pp, pt, frac(t0, start)));
+            }
+            if start <= t && t <= end {
+                points.push(pt.clone());
+            }
+            if let Some((pp, t0)) = prev
+                && t0 < end
+                && end < t
+            {
+                points.push(interpolate(pp, pt, frac(t0, end)));
+            }
+            prev = Some((pt, t));
+        }
+        Segment::new(points)
+    }
+
+    /// Returns the index of the point closest to `(lat, lon)` and its
+    /// haversine distance in metres, or `None` for an empty segment.
+    pub fn nearest_point(&self, lat: f64, lon: f64) -> Option<(usize, f64)> {
+        let query = trkpt::TrackPoint {
+            lat,
+            lon,
+            ..Default::default()
+        };
+        self.points
+            .iter()
+            .map(|pt| haversine_m(&query, pt))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Snaps `(lat, lon)` onto the line through the segment's points.
+    /// Returns the index of the first point of the closest leg, the distance
+    /// in metres and the closest point on that leg, interpolated as in
+    /// [`Segment::point_at_distance`]. Legs are straight lines in a local
+    /// equirectangular projection around the query, which is accurate for
+    /// legs up to a few kilometres. `None` for an empty segment.
+    pub fn nearest_point_on_line(
+        &self,
+        lat: f64,
+        lon: f64,
+    ) -> Option<(usize, f64, trkpt::TrackPoint)> {
+        if let [pt] = self.points.as_slice() {
+            return Some((0, geo::haversine_m(lat, lon, pt.lat, pt.lon), pt.clone()));
+        }
+
+        let scale = lat.to_radians().cos();
         self.points
             .windows(2)
-            .map(|w| haversine_m(&w[0], &w[1]))
-            .sum()
+            .enumerate()
+            .map(|(i, w)| {
+                let (ax, ay) = ((w[0].lon - lon) * scale, w[0].lat - lat);
+                let (dx, dy) = ((w[1].lon - w[0].lon) * scale, w[1].lat - w[0].lat);
+                let len2 = dx * dx + dy * dy;
+                let frac = if len2 > 0.0 {
+                    (-(ax * dx + ay * dy) / len2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let pt = match frac {
+                    0.0 => w[0].clone(),
+                    1.0 => w[1].clone(),
+                    _ => interpolate(&w[0], &w[1], frac),
+                };
+                (i, geo::haversine_m(lat, lon, pt.lat, pt.lon), pt)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
     }
 
-    pub fn total_ascent_descent_m(&self) -> (f64, f64) {
-        let mut ascent = 0.0;
-        let mut descent = 0.0;
+    /// Returns the initial compass bearing in degrees (`0..360`, north is
+    /// 0, east 90) from point `index` to the next one. `None` for the last
+    /// point, an index past the end, or coincident points.
+    pub fn bearing_at(&self, index: usize) -> Option<f64> {
+        let pa = self.points.get(index)?;
+        let pb = self.points.get(index + 1)?;
+        if pa.lat == pb.lat && pa.lon == pb.lon {
+            return None;
+        }
+
+        let (lat1, lat2) = (pa.lat.to_radians(), pb.lat.to_radians());
+        let dlon = (pb.lon - pa.lon).to_radians();
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+        Some(y.atan2(x).to_degrees().rem_euclid(360.0))
+    }
+
+    /// Returns `(cumulative distance, elevation)` for every point that has an
+    /// elevation. Points without `ele` are omitted but still count towards the
+    /// cumulative distance.
+    pub fn elevation_profile(&self) -> Vec<ElevationSample> {
+        let mut distance_m = 0.0;
+        let mut samples = Vec::new();
+
+        for (i, pt) in self.points.iter().enumerate() {
+            if i > 0 {
+                distance_m += haversine_m(&self.points[i - 1], pt);
+            }
+            if let Some(elevation_m) = pt.ele {
+                samples.push(ElevationSample {
+                    distance_m,
+                    elevation_m,
+                });
+            }
+        }
+        samples
+    }
+
+    /// Returns the grade of each leg as `ele_delta / horizontal_distance * 100`.
+    /// Legs with a missing elevation or zero horizontal distance are skipped,
+    /// but still count towards the cumulative distance.
+    pub fn grade_profile(&self) -> Vec<GradeSample> {
+        let mut distance_m = 0.0;
+        let mut samples = Vec::new();
 
         for w in self.points.windows(2) {
-            let a = &w[0];
-            let b = &w[1];
+            let d = haversine_m(&w[0], &w[1]);
+            distance_m += d;
 
-            let (Some(e1), Some(e2)) = (a.ele, b.ele) else {
+            let (Some(e1), Some(e2)) = (w[0].ele, w[1].ele) else {
                 continue;
             };
+            if d > 0.0 {
+                samples.push(GradeSample {
+                    distance_m,
+                    grade_percent: (e2 - e1) / d * 100.0,
+                });
+            }
+        }
+        samples
+    }
+
+    /// Returns the grade in percent of each leg. Legs with a missing elevation
+    /// or zero horizontal distance are skipped, so the result may be shorter
+    /// than the number of legs. See [`Segment::grade_profile`] for the
+    /// distance of each entry.
+    pub fn grades(&self) -> Vec<f64> {
+        self.grade_profile()
+            .into_iter()
+            .map(|s| s.grade_percent)
+            .collect()
+    }
+
+    /// Returns the steepest uphill grade in percent, if any leg has one.
+    pub fn max_grade(&self) -> Option<f64> {
+        self.grades().into_iter().reduce(f64::max)
+    }
+
+    /// Returns the pace in minutes per kilometre from the first to the last
+    /// timestamped point, over the whole segment distance. `None` without
+    /// two distinct timestamps or when the distance is zero.
+    pub fn avg_pace_min_per_km(&self) -> Option<f64> {
+        let first = self.points.iter().find_map(|p| p.timestamp())?;
+        let last = self.points.iter().rev().find_map(|p| p.timestamp())?;
+        pace_min_per_km(self.total_distance_m(), (last - first).num_milliseconds())
+    }
+
+    /// Returns the pace of each leg in minutes per kilometre. Legs without a
+    /// usable time difference or with zero distance are skipped, but still
+    /// count towards the cumulative distance.
+    pub fn pace_profile(&self) -> Vec<PaceSample> {
+        let mut distance_m = 0.0;
+        let mut samples = Vec::new();
+
+        for w in self.points.windows(2) {
+            let d = haversine_m(&w[0], &w[1]);
+            distance_m += d;
 
-            let delta = e2 - e1;
-            if delta > 0.0 {
-                ascent += delta;
-            } else if delta < 0.0 {
-                descent += -delta;
+            let Some(ms) = w[0]
+                .timestamp()
+                .zip(w[1].timestamp())
+                .map(|(t0, t1)| (t1 - t0).num_milliseconds())
+            else {
+                continue;
+            };
+            if let Some(pace_min_per_km) = pace_min_per_km(d, ms) {
+                samples.push(PaceSample {
+                    distance_m,
+                    pace_min_per_km,
+                });
+            }
+        }
+        samples
+    }
+
+    /// Returns one speed in m/s per point: the speed over the leg arriving
+    /// at it. The first point, and points whose leg lacks a usable time
+    /// difference, get `None`.
+    pub fn speed_profile(&self) -> Vec<Option<f64>> {
+        std::iter::once(None)
+            .chain(self.points.windows(2).map(|w| speed_mps(&w[0], &w[1])))
+            .take(self.points.len())
+            .collect()
+    }
+
+    /// Drops points closer than `distance_threshold_m` to the previously kept
+    /// point, keeping the first point of each run.
+    pub fn deduplicate_consecutive(&self, distance_threshold_m: f64) -> Segment {
+        let mut points: Vec<trkpt::TrackPoint> = Vec::with_capacity(self.points.len());
+        for pt in &self.points {
+            match points.last() {
+                Some(last) if haversine_m(last, pt) < distance_threshold_m => {}
+                _ => points.push(pt.clone()),
+            }
+        }
+        Segment::new(points)
+    }
+
+    /// Keeps only the points for which `predicate` returns `true`.
+    pub fn filter_points<F: Fn(&trkpt::TrackPoint) -> bool>(&self, predicate: F) -> Segment {
+        Segment::new(
+            self.points
+                .iter()
+                .filter(|pt| predicate(pt))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Drops points whose `hdop` exceeds `max_hdop`. Points without an
+    /// `hdop` are kept.
+    pub fn filter_accuracy(&self, max_hdop: f64) -> Segment {
+        self.filter_points(|pt| pt.hdop.is_none_or(|hdop| hdop <= max_hdop))
+    }
+
+    /// Drops every point whose speed to its predecessor or to its successor
+    /// exceeds `max_speed_kmh`. Both ends of an impossible leg go, so the
+    /// points either side of a glitch are dropped along with it. Legs
+    /// without a usable time difference never count as too fast.
+    pub fn remove_speed_outliers(&self, max_speed_kmh: f64) -> Segment {
+        let too_fast: Vec<bool> = self
+            .points
+            .windows(2)
+            .map(|w| w[0].speed_to(&w[1]).is_some_and(|v| v > max_speed_kmh))
+            .collect();
+
+        Segment::new(
+            self.points
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| {
+                    let to_prev = i > 0 && too_fast[i - 1];
+                    let to_next = too_fast.get(i).copied().unwrap_or(false);
+                    !(to_prev || to_next)
+                })
+                .map(|(_, pt)| pt.clone())
+                .collect(),
+        )
+    }
+
+    /// Returns inclusive index ranges `(start, end)` over which every leg is
+    /// at most `max_speed_kmh` and the points span at least `min_duration`,
+    /// e.g. rest stops. Legs without a usable time difference end a range.
+    pub fn detect_stationary_periods(
+        &self,
+        min_duration: Duration,
+        max_speed_kmh: f64,
+    ) -> Vec<(usize, usize)> {
+        let mut periods = Vec::new();
+        let mut run_start: Option<usize> = None;
+
+        let mut close = |start: Option<usize>, end: usize| {
+            let Some(start) = start else {
+                return;
+            };
+            let span = self.points[start]
+                .timestamp()
+                .zip(self.points[end].timestamp())
+                .and_then(|(t0, t1)| (t1 - t0).to_std().ok());
+            if span.is_some_and(|span| span >= min_duration) {
+                periods.push((start, end));
+            }
+        };
+
+        for i in 1..self.points.len() {
+            match self.points[i - 1].speed_to(&self.points[i]) {
+                Some(v) if v <= max_speed_kmh => {
+                    run_start.get_or_insert(i - 1);
+                }
+                _ => close(run_start.take(), i - 1),
             }
         }
-        (ascent, descent)
+        close(run_start, self.points.len().saturating_sub(1));
+
+        periods
+    }
+
+    /// Returns the segment traversed backwards. Timestamps are kept as they
+    /// are, so they run in decreasing order afterwards.
+    pub fn reverse(&self) -> Segment {
+        Segment::new(self.points.iter().rev().cloned().collect())
+    }
+
+    /// Splits the segment before point `index`. An `index` of `0` or past the
+    /// end leaves one side empty.
+    pub fn split_at_index(&self, index: usize) -> (Segment, Segment) {
+        let (head, tail) = self.points.split_at(index.min(self.points.len()));
+        (Segment::new(head.to_vec()), Segment::new(tail.to_vec()))
+    }
+
+    /// Appends the points of `other` after those of `self`. The merged
+    /// segment's distance includes the leg joining the two, so it is the sum
+    /// of both distances plus the gap between `self`'s last point and
+    /// `other`'s first.
+    pub fn merge(self, other: Segment) -> Segment {
+        let mut points = self.points;
+        points.extend(other.points);
+        Segment::new(points)
+    }
+
+    /// Splits the segment wherever two consecutive timestamped points are
+    /// more than `max_gap` apart in time. Points without a timestamp never
+    /// trigger a split and stay with the points before them.
+    pub fn split_by_time_gap(&self, max_gap: Duration) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut current: Vec<trkpt::TrackPoint> = Vec::new();
+        let mut last_time: Option<DateTime<FixedOffset>> = None;
+
+        for pt in &self.points {
+            if let Some(t) = pt.timestamp() {
+                let gap = last_time.and_then(|t0| (t - t0).to_std().ok());
+                if gap.is_some_and(|gap| gap > max_gap) {
+                    segments.push(Segment::new(std::mem::take(&mut current)));
+                }
+                last_time = Some(t);
+            }
+            current.push(pt.clone());
+        }
+        if !current.is_empty() {
+            segments.push(Segment::new(current));
+        }
+        segments
+    }
+
+    /// Returns one grade per point, measured as the elevation change over a
+    /// `window_m` distance window centred on the point (clamped at the
+    /// segment ends). Elevation is interpolated from the points that have
+    /// one; the result is empty when fewer than two points carry elevation.
+    pub fn smoothed_grades(&self, window_m: f64) -> Vec<f64> {
+        let profile = self.elevation_profile();
+        if profile.len() < 2 {
+            return Vec::new();
+        }
+        let (first, last) = (profile[0], profile[profile.len() - 1]);
+
+        let ele_at = |d: f64| {
+            let i = profile
+                .partition_point(|s| s.distance_m < d)
+                .clamp(1, profile.len() - 1);
+            let (a, b) = (&profile[i - 1], &profile[i]);
+            let span = b.distance_m - a.distance_m;
+            if span > 0.0 {
+                a.elevation_m + (b.elevation_m - a.elevation_m) * (d - a.distance_m) / span
+            } else {
+                b.elevation_m
+            }
+        };
+
+        let mut distance_m = 0.0;
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(i, pt)| {
+                if i > 0 {
+                    distance_m += haversine_m(&self.points[i - 1], pt);
+                }
+                let lo = (distance_m - window_m / 2.0).max(first.distance_m);
+                let hi = (distance_m + window_m / 2.0).min(last.distance_m);
+                if hi > lo {
+                    (ele_at(hi) - ele_at(lo)) / (hi - lo) * 100.0
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a copy of the segment with each elevation replaced by the mean
+    /// of the `window` points centred on it. The window is clamped at the
+    /// segment edges and points without `ele` stay `None`.
+    pub fn smooth_elevation(&self, window: usize) -> Segment {
+        let half = window / 2;
+        let points = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, pt)| {
+                let mut pt = pt.clone();
+                if pt.ele.is_some() {
+                    let lo = i.saturating_sub(half);
+                    let hi = (i + window.saturating_sub(half)).clamp(i + 1, self.points.len());
+                    let (sum, n) = self.points[lo..hi]
+                        .iter()
+                        .filter_map(|p| p.ele)
+                        .fold((0.0, 0usize), |(sum, n), e| (sum + e, n + 1));
+                    pt.ele = Some(sum / n as f64);
+                }
+                pt
+            })
+            .collect();
+        Segment::new(points)
+    }
+
+    /// Returns a segment with points every `interval_m` metres along this
+    /// one, interpolated as in [`Segment::point_at_distance`]. The first and
+    /// last points are always kept, so the final leg may be shorter.
+    /// A non-positive interval or a segment of fewer than two points is
+    /// returned unchanged.
+    pub fn resample_by_distance(&self, interval_m: f64) -> Segment {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return self.clone();
+        };
+        if interval_m <= 0.0 || self.points.len() < 2 {
+            return self.clone();
+        }
+        let total = self.total_distance_m();
+
+        let mut points = vec![first.clone()];
+        let mut travelled = 0.0;
+        let mut k = 1;
+        for w in self.points.windows(2) {
+            let d = haversine_m(&w[0], &w[1]);
+            loop {
+                let target = k as f64 * interval_m;
+                // Leave a target that lands on the end to the last point.
+                if target >= travelled + d || target >= total - RESAMPLE_EPSILON_M {
+                    break;
+                }
+                points.push(interpolate(&w[0], &w[1], (target - travelled) / d));
+                k += 1;
+            }
+            travelled += d;
+        }
+        points.push(last.clone());
+        Segment::new(points)
+    }
+
+    /// Returns a segment with points every `interval` from the first
+    /// timestamp to the last, interpolated between the timestamped points
+    /// that bracket each time (see [`Segment::point_at_time`]). Points
+    /// without a timestamp are ignored and timestamps are assumed to
+    /// increase. Fails with [`Error::InvalidData`] if the segment has no
+    /// timestamps or `interval` is zero.
+    pub fn resample_by_time(&self, interval: Duration) -> Result<Segment, Error> {
+        let timed: Vec<_> = self
+            .points
+            .iter()
+            .filter_map(|pt| Some((pt, pt.timestamp()?)))
+            .collect();
+        let (Some(&(first, start)), Some(&(last, end))) = (timed.first(), timed.last()) else {
+            return Err(Error::InvalidData {
+                message: "segment has no timestamps.".into(),
+            });
+        };
+        let step = TimeDelta::from_std(interval)
+            .ok()
+            .filter(|step| *step > TimeDelta::zero())
+            .ok_or_else(|| Error::InvalidData {
+                message: "resample interval must be positive.".into(),
+            })?;
+
+        let mut points = vec![first.clone()];
+        let mut target = start + step;
+        for w in timed.windows(2) {
+            let ((pa, t0), (pb, t1)) = (w[0], w[1]);
+            while target < t1 && target < end {
+                let frac =
+                    (target - t0).num_milliseconds() as f64 / (t1 - t0).num_milliseconds() as f64;
+                points.push(interpolate(pa, pb, frac));
+                target += step;
+            }
+        }
+        if timed.len() > 1 {
+            points.push(last.clone());
+        }
+        Ok(Segment::new(points))
+    }
+}
+
+/// Targets closer than this to the end of a segment are not resampled.
+const RESAMPLE_EPSILON_M: f64 = 1e-6;
+
+/// Length of the rolling mean used by [`Segment::normalized_power`].
+const NP_WINDOW_SECS: usize = 30;
+
+/// Longest gap between power readings that [`Segment::normalized_power`]
+/// fills in; longer ones end the run.
+const NP_MAX_GAP_SECS: i64 = 30;
+
+/// Fourth powers of the 30-second rolling means over a 1 Hz power series.
+fn rolling_fourth_powers(series: &[f64]) -> impl Iterator<Item = f64> + '_ {
+    series
+        .windows(NP_WINDOW_SECS)
+        .map(|w| (w.iter().sum::<f64>() / NP_WINDOW_SECS as f64).powi(4))
+}
+
+/// [`TrackPoint::speed_to`](trkpt::TrackPoint::speed_to) in m/s.
+fn speed_mps(pa: &trkpt::TrackPoint, pb: &trkpt::TrackPoint) -> Option<f64> {
+    pa.speed_to(pb).map(|v| v / 3.6)
+}
+
+/// Minutes per kilometre for covering `distance_m` in `ms` milliseconds, if
+/// both are positive.
+pub(crate) fn pace_min_per_km(distance_m: f64, ms: i64) -> Option<f64> {
+    (distance_m > 0.0 && ms > 0).then(|| ms as f64 / 60_000.0 / (distance_m / 1000.0))
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, n) = values.fold((0.0, 0usize), |(sum, n), v| (sum + v, n + 1));
+    (n > 0).then(|| sum / n as f64)
+}
+
+/// Linearly interpolates between `pa` (`frac == 0.0`) and `pb` (`frac == 1.0`).
+pub(crate) fn interpolate(
+    pa: &trkpt::TrackPoint,
+    pb: &trkpt::TrackPoint,
+    frac: f64,
+) -> trkpt::TrackPoint {
+    let lerp = |a: f64, b: f64| a + (b - a) * frac;
+
+    let time = match (pa.timestamp(), pb.timestamp()) {
+        (Some(t0), Some(t1)) => {
+            let span_ms = (t1 - t0).num_milliseconds() as f64;
+            let t = t0 + TimeDelta::milliseconds((span_ms * frac).round() as i64);
+            Some(t.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+        _ => None,
+    };
+
+    trkpt::TrackPoint {
+        lat: lerp(pa.lat, pb.lat),
+        lon: lerp(pa.lon, pb.lon),
+        time,
+        ele: pa.ele.zip(pb.ele).map(|(a, b)| lerp(a, b)),
+        ..Default::default()
     }
 }
 
-fn haversine_m(pa: &trkpt::TrackPoint, pb: &trkpt::TrackPoint) -> f64 {
-    let dlat = (pb.lat - pa.lat).to_radians();
-    let dlon = (pb.lon - pa.lon).to_radians();
+/// [`geo::haversine_m`] between two points.
+pub(crate) fn haversine_m(pa: &trkpt::TrackPoint, pb: &trkpt::TrackPoint) -> f64 {
+    geo::haversine_m(pa.lat, pa.lon, pb.lat, pb.lon)
+}
+
+/// Ellipsoidal distance between two points on WGS-84. Near-antipodal pairs,
+/// for which the iteration does not converge, fall back to [`haversine_m`].
+pub(crate) fn vincenty_m(pa: &trkpt::TrackPoint, pb: &trkpt::TrackPoint) -> f64 {
+    const A: f64 = 6_378_137.0;
+    const F: f64 = 1.0 / 298.257_223_563;
+    const B: f64 = A * (1.0 - F);
+    const MAX_ITERATIONS: usize = 200;
+
+    let l = (pb.lon - pa.lon).to_radians();
+    let u1 = ((1.0 - F) * pa.lat.to_radians().tan()).atan();
+    let u2 = ((1.0 - F) * pb.lat.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
 
-    let lat1 = pa.lat.to_radians();
-    let lat2 = pb.lat.to_radians();
-    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    let mut lambda = l;
+    for _ in 0..MAX_ITERATIONS {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            return 0.0;
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        // Both points on the equator: cos2_alpha is zero and so is the term.
+        let cos_2sigma_m = if cos2_alpha != 0.0 {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos2_alpha
+        } else {
+            0.0
+        };
+        let c = F / 16.0 * cos2_alpha * (4.0 + F * (4.0 - 3.0 * cos2_alpha));
+        let prev = lambda;
+        lambda = l
+            + (1.0 - c)
+                * F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))));
+
+        if (lambda - prev).abs() < 1e-12 {
+            let u_sq = cos2_alpha * (A * A - B * B) / (B * B);
+            let big_a =
+                1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + big_b / 4.0
+                        * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))
+                            - big_b / 6.0
+                                * cos_2sigma_m
+                                * (-3.0 + 4.0 * sin_sigma.powi(2))
+                                * (-3.0 + 4.0 * cos_2sigma_m.powi(2))));
+            return B * big_a * (sigma - delta_sigma);
+        }
+    }
 
-    let c = 2.0 * h.sqrt().atan2((1.0 - h).sqrt());
-    EARTH_RADIUS_M * c
+    haversine_m(pa, pb)
 }
 
 #[test]
@@ -68,12 +959,14 @@ fn segment_distance_basic() {
             lon: 0.0,
             time: None,
             ele: None,
+            ..Default::default()
         },
         TrackPoint {
             lat: 0.0,
             lon: 0.001, // ~111m
             time: None,
             ele: None,
+            ..Default::default()
         },
     ];
 
@@ -93,18 +986,21 @@ fn segment_ascent_descent_basic() {
             lon: 0.0,
             ele: Some(100.0),
             time: None,
+            ..Default::default()
         },
         TrackPoint {
             lat: 0.0,
             lon: 0.0,
             ele: Some(120.0),
             time: None,
+            ..Default::default()
         },
         TrackPoint {
             lat: 0.0,
             lon: 0.0,
             ele: Some(110.0),
             time: None,
+            ..Default::default()
         },
     ];
 
@@ -125,18 +1021,21 @@ fn segment_ascent_descent_with_missing_ele() {
             lon: 0.0,
             ele: Some(100.0),
             time: None,
+            ..Default::default()
         },
         TrackPoint {
             lat: 0.0,
             lon: 0.0,
             ele: None,
             time: None,
+            ..Default::default()
         },
         TrackPoint {
             lat: 0.0,
             lon: 0.0,
             ele: Some(130.0),
             time: None,
+            ..Default::default()
         },
     ];
 
@@ -146,3 +1045,857 @@ fn segment_ascent_descent_with_missing_ele() {
     assert_eq!(up, 0.0);
     assert_eq!(down, 0.0);
 }
+
+#[test]
+fn segment_smooth_elevation_sawtooth() {
+    use super::trkpt::TrackPoint;
+
+    let pts: Vec<TrackPoint> = (0..50)
+        .map(|i| TrackPoint {
+            lat: 0.0,
+            lon: 0.0,
+            ele: Some(if i % 2 == 0 { 100.0 } else { 110.0 }),
+            time: None,
+            ..Default::default()
+        })
+        .collect();
+
+    let seg = Segment::new(pts);
+    let (raw_up, _) = seg.total_ascent_descent_m();
+    let (up, down) = seg.smooth_elevation(20).total_ascent_descent_m();
+
+    assert_eq!(raw_up, 250.0);
+    assert!(up < 5.0);
+    assert!(down < 5.0);
+}
+
+#[test]
+fn segment_grade_profile_ten_percent_ramp() {
+    use super::trkpt::TrackPoint;
+
+    let leg = haversine_m(
+        &TrackPoint {
+            lat: 0.0,
+            lon: 0.0,
+            ele: None,
+            time: None,
+            ..Default::default()
+        },
+        &TrackPoint {
+            lat: 0.0,
+            lon: 0.001,
+            ele: None,
+            time: None,
+            ..Default::default()
+        },
+    );
+    let pts = (0..4)
+        .map(|i| TrackPoint {
+            lat: 0.0,
+            lon: 0.001 * i as f64,
+            ele: Some(leg * 0.1 * i as f64),
+            time: None,
+            ..Default::default()
+        })
+        .collect();
+
+    let profile = Segment::new(pts).grade_profile();
+
+    assert_eq!(profile.len(), 3);
+    for (i, s) in profile.iter().enumerate() {
+        assert!((s.grade_percent - 10.0).abs() < 1e-6);
+        assert!((s.distance_m - leg * (i + 1) as f64).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn segment_elevation_profile_two_points() {
+    use super::trkpt::TrackPoint;
+
+    let pts = vec![
+        TrackPoint {
+            lat: 0.0,
+            lon: 0.0,
+            ele: Some(100.0),
+            time: None,
+            ..Default::default()
+        },
+        TrackPoint {
+            lat: 0.0,
+            lon: 0.001,
+            ele: Some(120.0),
+            time: None,
+            ..Default::default()
+        },
+    ];
+    let leg = haversine_m(&pts[0], &pts[1]);
+
+    let profile = Segment::new(pts).elevation_profile();
+
+    assert_eq!(profile.len(), 2);
+    assert_eq!(profile[0].distance_m, 0.0);
+    assert_eq!(profile[0].elevation_m, 100.0);
+    assert_eq!(profile[1].distance_m, leg);
+    assert_eq!(profile[1].elevation_m, 120.0);
+}
+
+#[test]
+fn segment_point_at_distance_midpoint() {
+    use super::trkpt::TrackPoint;
+
+    let pts = vec![
+        TrackPoint {
+            lat: 0.0,
+            lon: 0.0,
+            ele: Some(100.0),
+            time: Some("2024-01-01T00:00:00Z".into()),
+            ..Default::default()
+        },
+        TrackPoint {
+            lat: 0.0,
+            lon: 0.002,
+            ele: Some(120.0),
+            time: Some("2024-01-01T00:01:00Z".into()),
+            ..Default::default()
+        },
+    ];
+    let seg = Segment::new(pts);
+    let len = seg.total_distance_m();
+
+    let mid = seg.point_at_distance(len / 2.0).unwrap();
+
+    assert_eq!(mid.lat, 0.0);
+    assert!((mid.lon - 0.001).abs() < 1e-9);
+    assert!((mid.ele.unwrap() - 110.0).abs() < 1e-9);
+    assert_eq!(mid.time.as_deref(), Some("2024-01-01T00:00:30Z"));
+    assert!(seg.point_at_distance(len + 1.0).is_none());
+}
+
+#[test]
+fn segment_elevation_range() {
+    use super::trkpt::TrackPoint;
+
+    let pt = |ele| TrackPoint {
+        lat: 0.0,
+        lon: 0.0,
+        ele,
+        time: None,
+        ..Default::default()
+    };
+
+    let none = Segment::new(vec![pt(None), pt(None)]);
+    assert_eq!(none.elevation_range(), None);
+    assert_eq!(none.max_elevation(), None);
+
+    let single = Segment::new(vec![pt(Some(42.0))]);
+    assert_eq!(single.elevation_range(), Some((42.0, 42.0)));
+
+    let mixed = Segment::new(vec![
+        pt(Some(120.0)),
+        pt(None),
+        pt(Some(80.0)),
+        pt(Some(95.0)),
+    ]);
+    assert_eq!(mixed.min_elevation(), Some(80.0));
+    assert_eq!(mixed.max_elevation(), Some(120.0));
+}
+
+#[test]
+fn segment_smoothed_grades_constant_climb() {
+    use super::trkpt::TrackPoint;
+
+    let pts: Vec<TrackPoint> = (0..20)
+        .map(|i| TrackPoint {
+            lat: 0.0,
+            lon: 0.001 * i as f64,
+            ele: None,
+            time: None,
+            ..Default::default()
+        })
+        .collect();
+    let leg = haversine_m(&pts[0], &pts[1]);
+    let pts = pts
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| TrackPoint {
+            ele: Some(leg * 0.05 * i as f64),
+            ..p
+        })
+        .collect();
+
+    let grades = Segment::new(pts).smoothed_grades(500.0);
+
+    assert_eq!(grades.len(), 20);
+    for g in grades {
+        assert!((g - 5.0).abs() < 1e-6, "grade {g}");
+    }
+}
+
+#[test]
+fn segment_grades_hundred_metre_run() {
+    use super::trkpt::TrackPoint;
+
+    // 100 m of latitude along a meridian.
+    let run_deg = 100.0 / geo::EARTH_RADIUS_M.to_radians();
+    let pts = vec![
+        TrackPoint {
+            lat: 0.0,
+            lon: 0.0,
+            ele: Some(0.0),
+            ..Default::default()
+        },
+        TrackPoint {
+            lat: run_deg,
+            lon: 0.0,
+            ele: Some(10.0),
+            ..Default::default()
+        },
+        TrackPoint {
+            lat: run_deg,
+            lon: 0.0,
+            ele: Some(12.0),
+            ..Default::default()
+        },
+        TrackPoint {
+            lat: 2.0 * run_deg,
+            lon: 0.0,
+            ele: Some(7.0),
+            ..Default::default()
+        },
+    ];
+
+    let seg = Segment::new(pts);
+    let grades = seg.grades();
+
+    assert_eq!(grades.len(), 2);
+    assert!((grades[0] - 10.0).abs() < 1e-9);
+    assert!((grades[1] + 5.0).abs() < 1e-9);
+    assert!((seg.max_grade().unwrap() - 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn segment_split_at_index_midpoint() {
+    use super::trkpt::TrackPoint;
+
+    let pts: Vec<TrackPoint> = (0..4)
+        .map(|i| TrackPoint {
+            lon: i as f64 * 0.001,
+            ..Default::default()
+        })
+        .collect();
+    let seg = Segment::new(pts);
+
+    let (a, b) = seg.split_at_index(2);
+    assert_eq!(a.points(), &seg.points()[..2]);
+    assert_eq!(b.points(), &seg.points()[2..]);
+
+    let (a, b) = seg.split_at_index(0);
+    assert_eq!((a.point_count(), b.point_count()), (0, 4));
+    let (a, b) = seg.split_at_index(10);
+    assert_eq!((a.point_count(), b.point_count()), (4, 0));
+}
+
+#[test]
+fn segment_split_by_time_gap_five_minutes() {
+    use super::trkpt::TrackPoint;
+
+    let at = |lon: f64, time: &str| TrackPoint {
+        lon,
+        time: Some(time.to_string()),
+        ..Default::default()
+    };
+    let seg = Segment::new(vec![
+        at(0.0, "2024-01-01T10:00:00Z"),
+        at(0.001, "2024-01-01T10:00:10Z"),
+        at(0.002, "2024-01-01T10:05:10Z"),
+        at(0.003, "2024-01-01T10:05:20Z"),
+        TrackPoint {
+            lon: 0.004,
+            ..Default::default()
+        },
+    ]);
+
+    let parts = seg.split_by_time_gap(Duration::from_secs(60));
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].point_count(), 2);
+    assert_eq!(parts[1].point_count(), 3);
+    assert_eq!(seg.split_by_time_gap(Duration::from_secs(300)).len(), 1);
+}
+
+#[test]
+fn segment_merge_includes_junction_leg() {
+    use super::trkpt::TrackPoint;
+
+    let at = |lon: f64| TrackPoint {
+        lon,
+        ..Default::default()
+    };
+    let a = Segment::new(vec![at(0.0), at(0.001)]);
+    let b = Segment::new(vec![at(0.003), at(0.004)]);
+    let junction = haversine_m(a.last().unwrap(), b.first().unwrap());
+    let expected = a.total_distance_m() + b.total_distance_m() + junction;
+
+    let merged = a.merge(b);
+
+    assert_eq!(merged.point_count(), 4);
+    assert!((merged.total_distance_m() - expected).abs() < 1e-9);
+}
+
+#[test]
+fn segment_reverse_swaps_ascent_descent() {
+    use super::trkpt::TrackPoint;
+
+    let pts: Vec<TrackPoint> = [100.0, 130.0, 120.0]
+        .iter()
+        .enumerate()
+        .map(|(i, &ele)| TrackPoint {
+            lon: i as f64 * 0.001,
+            ele: Some(ele),
+            ..Default::default()
+        })
+        .collect();
+    let seg = Segment::new(pts);
+
+    let reversed = seg.reverse();
+
+    assert_eq!(reversed.first(), seg.last());
+    assert_eq!(reversed.reverse(), seg);
+    assert_eq!(seg.total_ascent_descent_m(), (30.0, 10.0));
+    assert_eq!(reversed.total_ascent_descent_m(), (10.0, 30.0));
+}
+
+#[test]
+fn segment_vincenty_matches_reference() {
+    use super::trkpt::TrackPoint;
+
+    let at = |lat: f64, lon: f64| TrackPoint {
+        lat,
+        lon,
+        ..Default::default()
+    };
+    let dms = |d: f64, m: f64, s: f64| d.signum() * (d.abs() + m / 60.0 + s / 3600.0);
+
+    // Flinders Peak to Buninyong, the classic test line from Vincenty's paper
+    // as reworked by Geoscience Australia: 54 972.271 m.
+    let flinders = at(dms(-37.0, 57.0, 3.72030), dms(144.0, 25.0, 29.52440));
+    let buninyong = at(dms(-37.0, 39.0, 10.15610), dms(143.0, 55.0, 35.38390));
+    assert!((vincenty_m(&flinders, &buninyong) - 54_972.271).abs() < 1e-3);
+
+    // One degree of meridian from the equator, ~110.6 km: 110 574.389 m.
+    let seg = Segment::new(vec![at(0.0, 0.0), at(1.0, 0.0)]);
+    assert!((seg.total_distance_vincenty_m() - 110_574.389).abs() < 1e-3);
+    assert!((seg.total_distance_m() - 110_574.389).abs() > 100.0);
+
+    // Near-antipodal points fall back to haversine instead of diverging.
+    let (a, b) = (at(0.0, 0.0), at(0.5, 179.7));
+    assert_eq!(vincenty_m(&a, &b), haversine_m(&a, &b));
+}
+
+#[test]
+fn segment_split_by_time_gap_skips_untimed_points() {
+    use super::trkpt::TrackPoint;
+
+    let pt = |lon: f64, time: Option<&str>| TrackPoint {
+        lon,
+        time: time.map(str::to_string),
+        ..Default::default()
+    };
+    let seg = Segment::new(vec![
+        pt(0.0, Some("2024-01-01T10:00:00Z")),
+        pt(0.001, None),
+        pt(0.002, Some("2024-01-01T10:10:00Z")),
+        pt(0.003, None),
+        pt(0.004, None),
+    ]);
+
+    let parts = seg.split_by_time_gap(Duration::from_secs(60));
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].point_count(), 2);
+    assert_eq!(parts[1].first().unwrap().lon, 0.002);
+
+    let untimed = Segment::new(vec![pt(0.0, None), pt(0.001, None)]);
+    assert_eq!(untimed.split_by_time_gap(Duration::ZERO).len(), 1);
+}
+
+#[test]
+fn segment_detect_stationary_periods_single_stop() {
+    use super::trkpt::TrackPoint;
+
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let step_deg = 100.0 / geo::EARTH_RADIUS_M.to_radians();
+    // Moving at 36 km/h for 90 s, stopped for 5 min 10 s, then moving again.
+    let lons = (0..10)
+        .map(|i| i as f64 * step_deg)
+        .chain((0..31).map(|i| 9.0 * step_deg + (i % 2) as f64 * 1e-6))
+        .chain((1..10).map(|i| (9 + i) as f64 * step_deg));
+    let pts = lons
+        .enumerate()
+        .map(|(i, lon)| TrackPoint {
+            lon,
+            time: Some((start + TimeDelta::seconds(10 * i as i64)).to_rfc3339()),
+            ..Default::default()
+        })
+        .collect();
+    let seg = Segment::new(pts);
+
+    let periods = seg.detect_stationary_periods(Duration::from_secs(300), 2.0);
+
+    assert_eq!(periods, [(9, 40)]);
+    assert!(
+        seg.detect_stationary_periods(Duration::from_secs(600), 2.0)
+            .is_empty()
+    );
+}
+
+#[test]
+fn segment_filter_accuracy_drops_poor_fix() {
+    let gpx = r#"<gpx><trk><trkseg>
+        <trkpt lat="0.0" lon="0.0"><hdop>0.9</hdop></trkpt>
+        <trkpt lat="0.001" lon="0.001"><hdop>12.5</hdop></trkpt>
+        <trkpt lat="0.0" lon="0.002"></trkpt>
+    </trkseg></trk></gpx>"#;
+    let track = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let seg = &track.segments()[0];
+    assert_eq!(seg.points()[1].hdop, Some(12.5));
+
+    let filtered = seg.filter_accuracy(5.0);
+
+    assert_eq!(filtered.point_count(), 2);
+    assert!(filtered.points().iter().all(|pt| pt.hdop != Some(12.5)));
+    assert!(filtered.total_distance_m() < seg.total_distance_m());
+}
+
+#[test]
+fn segment_nearest_point() {
+    use super::trkpt::TrackPoint;
+
+    let pts: Vec<TrackPoint> = (0..4)
+        .map(|i| TrackPoint {
+            lon: i as f64 * 0.001,
+            ..Default::default()
+        })
+        .collect();
+    let seg = Segment::new(pts);
+
+    let (i, d) = seg.nearest_point(0.0, 0.002).unwrap();
+    assert_eq!(i, 2);
+    assert!(d < 1e-9);
+
+    let (i, d) = seg.nearest_point(0.0001, 0.0014).unwrap();
+    assert_eq!(i, 1);
+    assert!(d > 0.0);
+    assert!(Segment::new(Vec::new()).nearest_point(0.0, 0.0).is_none());
+}
+
+#[test]
+fn segment_distance_formulas_agree_within_half_percent() {
+    use super::trkpt::TrackPoint;
+
+    // Portsmouth to London Bridge, roughly 100 km.
+    let seg = Segment::new(vec![
+        TrackPoint::builder(50.8198, -1.0880).build(),
+        TrackPoint::builder(51.5079, -0.0877).build(),
+    ]);
+
+    let haversine = seg.total_distance_with(DistanceFormula::Haversine);
+    let vincenty = seg.total_distance_with(DistanceFormula::Vincenty);
+
+    assert_eq!(haversine, seg.total_distance_m());
+    assert_eq!(vincenty, seg.total_distance_vincenty_m());
+    assert!(haversine != vincenty);
+    assert!((haversine - vincenty).abs() / vincenty < 0.005);
+}
+
+#[test]
+fn segment_caches_distance() {
+    use super::trkpt::TrackPoint;
+
+    let seg = Segment::new(vec![
+        TrackPoint::builder(0.0, 0.0).ele(10.0).build(),
+        TrackPoint::builder(0.0, 0.001).ele(15.0).build(),
+    ]);
+    assert!(!seg.is_distance_cached());
+
+    let first = seg.total_distance_m();
+    assert!(seg.is_distance_cached());
+    let second = seg.total_distance_m();
+
+    assert_eq!(first, second);
+    assert_eq!(seg.total_ascent_descent_m(), (5.0, 0.0));
+    assert_eq!(seg.total_ascent_descent_m(), (5.0, 0.0));
+
+    // Merging builds a new segment, so the cached total is not reused.
+    let merged = seg.merge(Segment::new(vec![TrackPoint::builder(0.0, 0.002).build()]));
+    assert!(!merged.is_distance_cached());
+    assert!(merged.total_distance_m() > first);
+}
+
+#[test]
+fn segment_bearing_at_compass_points() {
+    use super::trkpt::TrackPoint;
+
+    let seg = Segment::new(vec![
+        TrackPoint::builder(0.0, 0.0).build(),
+        TrackPoint::builder(0.001, 0.0).build(),
+        TrackPoint::builder(0.001, 0.001).build(),
+        TrackPoint::builder(0.0, 0.001).build(),
+        TrackPoint::builder(0.0, 0.0).build(),
+        TrackPoint::builder(0.0, 0.0).build(),
+    ]);
+
+    let bearings: Vec<Option<f64>> = (0..6).map(|i| seg.bearing_at(i)).collect();
+
+    for (got, want) in bearings[..4].iter().zip([0.0, 90.0, 180.0, 270.0]) {
+        assert!((got.unwrap() - want).abs() < 1e-6, "{got:?} vs {want}");
+    }
+    assert_eq!(bearings[4], None);
+    assert_eq!(bearings[5], None);
+}
+
+#[test]
+fn segment_point_at_time_midpoint() {
+    use super::trkpt::TrackPoint;
+
+    let seg = Segment::new(vec![
+        TrackPoint::builder(50.0, 1.0)
+            .time("2024-01-01T00:00:00Z")
+            .build(),
+        TrackPoint::builder(50.2, 1.4)
+            .time("2024-01-01T00:10:00Z")
+            .build(),
+    ]);
+    let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+
+    let pt = seg.point_at_time(at("2024-01-01T00:05:00Z")).unwrap();
+
+    assert!((pt.lat - 50.1).abs() < 1e-9);
+    assert!((pt.lon - 1.2).abs() < 1e-9);
+    assert_eq!(pt.time.as_deref(), Some("2024-01-01T00:05:00Z"));
+    assert!(seg.point_at_time(at("2024-01-01T00:10:01Z")).is_none());
+    assert!(seg.point_at_time(at("2023-12-31T23:59:59Z")).is_none());
+}
+
+#[test]
+fn segment_resample_by_distance_hundred_metres() {
+    use super::trkpt::TrackPoint;
+
+    // 1000 m along the equator, recorded at uneven spacing.
+    let lon_at = |m: f64| (m / geo::EARTH_RADIUS_M).to_degrees();
+    let seg = Segment::new(
+        [0.0, 130.0, 170.0, 420.0, 640.0, 655.0, 1000.0]
+            .iter()
+            .map(|&m| TrackPoint::builder(0.0, lon_at(m)).build())
+            .collect(),
+    );
+
+    let resampled = seg.resample_by_distance(100.0);
+
+    assert_eq!(resampled.point_count(), 11);
+    let ratio = resampled.total_distance_m() / seg.total_distance_m();
+    assert!((ratio - 1.0).abs() < 0.01);
+    for (i, pt) in resampled.points().iter().enumerate() {
+        assert!((pt.lon - lon_at(100.0 * i as f64)).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn segment_resample_by_time_ten_seconds() {
+    use super::trkpt::TrackPoint;
+
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let at = |s: i64| start + TimeDelta::seconds(s);
+    let seg = Segment::new(
+        [0, 7, 15, 31, 42, 44, 60]
+            .iter()
+            .map(|&s| {
+                TrackPoint::builder(0.0, s as f64 * 1e-4)
+                    .time(at(s).to_rfc3339())
+                    .build()
+            })
+            .collect(),
+    );
+
+    let resampled = seg.resample_by_time(Duration::from_secs(10)).unwrap();
+
+    assert_eq!(resampled.point_count(), 7);
+    for (i, pt) in resampled.points().iter().enumerate() {
+        assert_eq!(pt.timestamp(), Some(at(10 * i as i64)));
+        assert!((pt.lon - i as f64 * 1e-3).abs() < 1e-9);
+    }
+    assert!(matches!(
+        Segment::new(vec![TrackPoint::builder(0.0, 0.0).build()])
+            .resample_by_time(Duration::from_secs(10)),
+        Err(Error::InvalidData { .. })
+    ));
+    assert!(seg.resample_by_time(Duration::ZERO).is_err());
+}
+
+#[test]
+fn segment_speed_profile_constant_speed() {
+    use super::trkpt::TrackPoint;
+
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let mut points: Vec<_> = (0..5)
+        .map(|i| {
+            TrackPoint::builder(0.0, i as f64 * 1e-3)
+                .time((start + TimeDelta::seconds(20 * i)).to_rfc3339())
+                .build()
+        })
+        .collect();
+    points.push(TrackPoint::builder(0.0, 0.005).build());
+    let leg = haversine_m(&points[0], &points[1]);
+
+    let speeds = Segment::new(points).speed_profile();
+
+    assert_eq!(speeds.len(), 6);
+    assert_eq!(speeds[0], None);
+    for v in &speeds[1..5] {
+        assert!((v.unwrap() - leg / 20.0).abs() < 1e-6);
+    }
+    assert_eq!(speeds[5], None);
+    assert!(Segment::new(Vec::new()).speed_profile().is_empty());
+}
+
+#[test]
+fn segment_filter_points_by_elevation() {
+    use super::trkpt::TrackPoint;
+
+    let seg = Segment::new(
+        [
+            Some(450.0),
+            Some(520.0),
+            None,
+            Some(500.0),
+            Some(610.0),
+            Some(480.0),
+        ]
+        .into_iter()
+        .map(|ele| TrackPoint {
+            ele,
+            ..Default::default()
+        })
+        .collect(),
+    );
+
+    let high = seg.filter_points(|pt| pt.ele > Some(500.0));
+
+    assert_eq!(high.point_count(), 2);
+    assert_eq!(high[0].ele, Some(520.0));
+    assert_eq!(high[1].ele, Some(610.0));
+}
+
+#[test]
+fn segment_deduplicate_consecutive_stationary_run() {
+    use super::trkpt::TrackPoint;
+
+    let mut points = vec![TrackPoint::builder(50.0, -1.0).build(); 5];
+    points.push(TrackPoint::builder(50.0, -0.999).build());
+    let seg = Segment::new(points);
+
+    let deduped = seg.deduplicate_consecutive(1.0);
+
+    assert_eq!(deduped.point_count(), 2);
+    assert_eq!(deduped[1].lon, -0.999);
+    assert_eq!(deduped.total_distance_m(), seg.total_distance_m());
+}
+
+#[test]
+fn segment_avg_pace_five_minute_km() {
+    use super::trkpt::TrackPoint;
+
+    // 5 km along the equator in 25 minutes, one point per kilometre.
+    let start = DateTime::parse_from_rfc3339("2024-01-01T07:00:00Z").unwrap();
+    let seg = Segment::new(
+        (0..=5)
+            .map(|km| {
+                TrackPoint::builder(0.0, (km as f64 * 1000.0 / geo::EARTH_RADIUS_M).to_degrees())
+                    .time((start + TimeDelta::minutes(5 * km)).to_rfc3339())
+                    .build()
+            })
+            .collect(),
+    );
+
+    assert!((seg.avg_pace_min_per_km().unwrap() - 5.0).abs() < 1e-9);
+    let profile = seg.pace_profile();
+    assert_eq!(profile.len(), 5);
+    assert!(
+        profile
+            .iter()
+            .all(|s| (s.pace_min_per_km - 5.0).abs() < 1e-9)
+    );
+    assert!((profile[4].distance_m - 5000.0).abs() < 1e-6);
+    assert_eq!(
+        Segment::new(seg.points()[..1].to_vec()).avg_pace_min_per_km(),
+        None
+    );
+}
+
+#[test]
+fn segment_remove_speed_outliers_single_glitch() {
+    use super::trkpt::TrackPoint;
+
+    // Walking at about 5 km/h, one point every 10 s.
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let walk: Vec<TrackPoint> = (0..20)
+        .map(|i| {
+            TrackPoint::builder(50.0, -1.0 + i as f64 * 0.0002)
+                .time((start + TimeDelta::seconds(10 * i)).to_rfc3339())
+                .build()
+        })
+        .collect();
+    let mut points = walk.clone();
+    points[10].lat += 0.9; // ~100 km north for one reading
+
+    let cleaned = Segment::new(points).remove_speed_outliers(50.0);
+
+    // The glitch and the two points on either side of it.
+    assert_eq!(cleaned.point_count(), walk.len() - 3);
+    assert!(cleaned.points().iter().all(|p| p.lat == 50.0));
+    let original = Segment::new(walk).total_distance_m();
+    assert!((cleaned.total_distance_m() - original).abs() / original < 0.01);
+}
+
+#[test]
+fn segment_nearest_point_on_line_snaps_between_vertices() {
+    use super::trkpt::TrackPoint;
+
+    let seg = Segment::new(vec![
+        TrackPoint::builder(0.0, 0.0).build(),
+        TrackPoint::builder(0.0, 0.01).ele(100.0).build(),
+        TrackPoint::builder(0.01, 0.01).build(),
+    ]);
+
+    let (leg, d, pt) = seg.nearest_point_on_line(0.0005, 0.004).unwrap();
+    assert_eq!(leg, 0);
+    assert!((pt.lat - 0.0).abs() < 1e-12 && (pt.lon - 0.004).abs() < 1e-9);
+    assert!((d - geo::haversine_m(0.0, 0.0, 0.0005, 0.0)).abs() < 1e-3);
+
+    // Past the corner, the closest place is the vertex itself.
+    let (_, _, corner) = seg.nearest_point_on_line(-0.001, 0.011).unwrap();
+    assert_eq!(corner, seg[1]);
+    assert!(
+        Segment::new(Vec::new())
+            .nearest_point_on_line(0.0, 0.0)
+            .is_none()
+    );
+}
+
+#[test]
+fn segment_normalized_power_weights_variable_efforts() {
+    use super::trkpt::TrackPoint;
+
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let ride = |power: fn(i64) -> u16| {
+        Segment::new(
+            (0..120)
+                .map(|i| {
+                    TrackPoint::builder(0.0, i as f64 * 1e-4)
+                        .time((start + TimeDelta::seconds(i)).to_rfc3339())
+                        .power(power(i))
+                        .build()
+                })
+                .collect(),
+        )
+    };
+
+    let intervals = ride(|i| if i < 60 { 100 } else { 300 });
+    assert_eq!(intervals.avg_power(), Some(200.0));
+    assert!(intervals.normalized_power().unwrap() > 200.0);
+
+    let steady = ride(|_| 200);
+    assert!((steady.normalized_power().unwrap() - 200.0).abs() < 1e-9);
+}
+
+#[test]
+fn segment_normalized_power_needs_thirty_seconds() {
+    use super::trkpt::TrackPoint;
+
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let short = Segment::new(
+        (0..20)
+            .map(|i| {
+                TrackPoint::builder(0.0, 0.0)
+                    .time((start + TimeDelta::seconds(i)).to_rfc3339())
+                    .power(250)
+                    .build()
+            })
+            .collect(),
+    );
+    assert_eq!(short.normalized_power(), None);
+
+    // Sparse readings are held until the next one, so 5 points 10 s apart
+    // span 41 samples.
+    let sparse = Segment::new(
+        (0..5)
+            .map(|i| {
+                TrackPoint::builder(0.0, 0.0)
+                    .time((start + TimeDelta::seconds(10 * i)).to_rfc3339())
+                    .power(250)
+                    .build()
+            })
+            .collect(),
+    );
+    assert!((sparse.normalized_power().unwrap() - 250.0).abs() < 1e-9);
+}
+
+#[test]
+fn segment_normalized_power_splits_at_long_gaps() {
+    use super::trkpt::TrackPoint;
+
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let point = |secs: i64, power: u16| {
+        TrackPoint::builder(0.0, 0.0)
+            .time((start + TimeDelta::seconds(secs)).to_rfc3339())
+            .power(power)
+            .build()
+    };
+    // An hour at 200 W, a ten-hour stop, then another hour at 200 W. The
+    // stop must neither be materialised nor held at the last reading.
+    let mut points: Vec<_> = (0..3600).map(|i| point(i, 200)).collect();
+    points.push(point(3600, 400));
+    points.extend((0..3600).map(|i| point(3600 + 36_000 + i, 200)));
+
+    let np = Segment::new(points).normalized_power().unwrap();
+    assert!((np - 200.0).abs() < 1.0);
+
+    // Two short efforts on either side of a stop don't add up to a window.
+    let split = Segment::new(vec![point(0, 300), point(20, 300), point(7200, 300)]);
+    assert_eq!(split.normalized_power(), None);
+}
+
+#[test]
+fn segment_remove_speed_outliers_glitch_at_either_end() {
+    use super::trkpt::TrackPoint;
+
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let walk: Vec<TrackPoint> = (0..10)
+        .map(|i| {
+            TrackPoint::builder(50.0, -1.0 + i as f64 * 0.0002)
+                .time((start + TimeDelta::seconds(10 * i)).to_rfc3339())
+                .build()
+        })
+        .collect();
+
+    let mut first = walk.clone();
+    first[0].lat += 0.9;
+    let cleaned = Segment::new(first).remove_speed_outliers(50.0);
+    assert_eq!(cleaned.points(), &walk[2..]);
+
+    let mut last = walk.clone();
+    last[9].lat += 0.9;
+    let cleaned = Segment::new(last).remove_speed_outliers(50.0);
+    assert_eq!(cleaned.points(), &walk[..8]);
+
+    // Both ends of a single impossible leg are dropped.
+    let mut pair = walk[..2].to_vec();
+    pair[0].lat += 0.9;
+    assert!(Segment::new(pair).remove_speed_outliers(50.0).is_empty());
+    assert_eq!(
+        Segment::new(walk.clone())
+            .remove_speed_outliers(50.0)
+            .points(),
+        &walk[..]
+    );
+}
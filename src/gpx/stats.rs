@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+
+#[cfg(feature = "rayon")]
+use crate::gpx::Track;
+
+/// Summary figures for a whole track, as returned by
+/// [`Track::stats`](crate::gpx::Track::stats).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackStats {
+    pub segment_count: usize,
+    pub point_count: usize,
+    pub distance_m: f64,
+    pub ascent_m: f64,
+    pub descent_m: f64,
+    /// Time from the first to the last timestamped point, if any.
+    pub duration: Option<Duration>,
+    /// `distance_m` over `duration`, when the duration is non-zero.
+    pub avg_speed_kmh: Option<f64>,
+}
+
+impl TrackStats {
+    /// Fills in `duration` and `avg_speed_kmh` from the first and last
+    /// timestamps of the track.
+    pub(crate) fn set_timing(
+        &mut self,
+        first: Option<DateTime<FixedOffset>>,
+        last: Option<DateTime<FixedOffset>>,
+    ) {
+        self.duration = first.zip(last).and_then(|(t0, t1)| (t1 - t0).to_std().ok());
+        self.avg_speed_kmh = self
+            .duration
+            .filter(|d| !d.is_zero())
+            .map(|d| self.distance_m / d.as_secs_f64() * 3.6);
+    }
+}
+
+/// Computes [`Track::stats`](crate::gpx::Track::stats) for every track in parallel. The result is
+/// index-aligned with `tracks`.
+#[cfg(feature = "rayon")]
+pub fn compute_stats_batch(tracks: &[Track]) -> Vec<TrackStats> {
+    use rayon::prelude::*;
+
+    tracks.par_iter().map(Track::stats).collect()
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn compute_stats_batch_matches_serial() {
+    let tracks: Vec<Track> = (1..=8)
+        .map(|n| {
+            let gpx: String = (0..n * 10)
+                .map(|i| {
+                    format!(
+                        r#"<trkpt lat="0.0" lon="{}"><ele>{}</ele></trkpt>"#,
+                        i as f64 * 0.001,
+                        (i * 7 % 13) as f64
+                    )
+                })
+                .collect();
+            let gpx = format!("<gpx><trk><trkseg>{gpx}</trkseg></trk></gpx>");
+            crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap()
+        })
+        .collect();
+
+    let serial: Vec<TrackStats> = tracks.iter().map(Track::stats).collect();
+
+    assert_eq!(compute_stats_batch(&tracks), serial);
+}
+
+#[test]
+fn track_stats_matches_individual_totals() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"><ele>100</ele><time>2024-01-01T10:00:00Z</time></trkpt>
+              <trkpt lat="0.0" lon="0.01"><ele>120</ele></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.02"><ele>110</ele></trkpt>
+              <trkpt lat="0.0" lon="0.03"><ele>105</ele><time>2024-01-01T10:10:00Z</time></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let stats = track.stats();
+
+    assert_eq!(stats.segment_count, 2);
+    assert_eq!(stats.point_count, 4);
+    assert!((stats.distance_m - track.total_distance_m()).abs() < 1e-6);
+    assert_eq!((stats.ascent_m, stats.descent_m), (20.0, 5.0));
+    assert_eq!(stats.duration, Some(Duration::from_secs(600)));
+    let expected_kmh = stats.distance_m / 600.0 * 3.6;
+    assert!((stats.avg_speed_kmh.unwrap() - expected_kmh).abs() < 1e-9);
+
+    let untimed = crate::gpx::Track::new(Vec::new()).stats();
+    assert_eq!(untimed.duration, None);
+    assert_eq!(untimed.avg_speed_kmh, None);
+}
@@ -0,0 +1,377 @@
+use std::io::{BufRead, Write};
+
+use quick_xml::{
+    Reader, Writer,
+    events::{BytesDecl, BytesEnd, BytesStart, Event},
+};
+
+use crate::gpx::{
+    Error, Segment, Track, TrackPoint,
+    err::InternalError,
+    trkpt::{TextHandler, check_lat_lon, parse_number, read_text_string, skip_bom},
+    writer::write_text,
+};
+
+const TCX_NS: &str = "http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2";
+/// Stands in for the required activity and lap start times when a track
+/// has no timestamps.
+const EPOCH: &str = "1970-01-01T00:00:00Z";
+
+/// A `<Trackpoint>` being parsed. TCX puts the coordinates in child
+/// elements and may omit them entirely, e.g. while paused.
+#[derive(Default)]
+struct TcxPoint {
+    point: TrackPoint,
+    lat: Option<f64>,
+    lon: Option<f64>,
+}
+
+impl TcxPoint {
+    /// Returns the point, or `None` if it has no `<Position>`.
+    fn finish(self) -> Result<Option<TrackPoint>, InternalError> {
+        if self.lat.is_none() && self.lon.is_none() {
+            return Ok(None);
+        }
+        let (lat, lon) = check_lat_lon(self.lat, self.lon, "Trackpoint")?;
+        Ok(Some(TrackPoint {
+            lat,
+            lon,
+            ..self.point
+        }))
+    }
+}
+
+type TcxApplyfn = fn(&mut TcxPoint, &str) -> Result<(), InternalError>;
+
+fn apply_time(tp: &mut TcxPoint, s: &str) -> Result<(), InternalError> {
+    tp.point.time = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_lat(tp: &mut TcxPoint, s: &str) -> Result<(), InternalError> {
+    tp.lat = Some(parse_number(s, "LatitudeDegrees")?);
+    Ok(())
+}
+
+fn apply_lon(tp: &mut TcxPoint, s: &str) -> Result<(), InternalError> {
+    tp.lon = Some(parse_number(s, "LongitudeDegrees")?);
+    Ok(())
+}
+
+fn apply_altitude(tp: &mut TcxPoint, s: &str) -> Result<(), InternalError> {
+    tp.point.ele = Some(parse_number(s, "AltitudeMeters")?);
+    Ok(())
+}
+
+fn apply_cadence(tp: &mut TcxPoint, s: &str) -> Result<(), InternalError> {
+    tp.point.cadence = Some(parse_number(s, "Cadence")?);
+    Ok(())
+}
+
+fn apply_hr(tp: &mut TcxPoint, s: &str) -> Result<(), InternalError> {
+    tp.point.hr = Some(parse_number(s, "HeartRateBpm")?);
+    Ok(())
+}
+
+const HANDLERS: &[TextHandler<TcxPoint>] = &[
+    TextHandler {
+        tag: b"Time",
+        apply: apply_time,
+    },
+    TextHandler {
+        tag: b"LatitudeDegrees",
+        apply: apply_lat,
+    },
+    TextHandler {
+        tag: b"LongitudeDegrees",
+        apply: apply_lon,
+    },
+    TextHandler {
+        tag: b"AltitudeMeters",
+        apply: apply_altitude,
+    },
+    TextHandler {
+        tag: b"Cadence",
+        apply: apply_cadence,
+    },
+];
+
+fn find_handler(tag: &[u8]) -> Option<TcxApplyfn> {
+    HANDLERS.iter().find(|h| h.tag == tag).map(|h| h.apply)
+}
+
+/// Parses a Garmin Training Center (TCX) document. Each `<Track>` becomes a
+/// segment; `<Trackpoint>`s without a `<Position>` are skipped.
+pub fn parse_tcx<R: BufRead>(mut reader: R) -> Result<Track, Error> {
+    skip_bom(&mut reader)?;
+    let mut xml = Reader::from_reader(reader);
+    xml.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut segments = Vec::new();
+    let mut current_points: Vec<TrackPoint> = Vec::new();
+    let mut current: Option<TcxPoint> = None;
+    let mut handler: Option<TcxApplyfn> = None;
+    let mut in_hr = false;
+
+    loop {
+        match xml
+            .read_event_into(&mut buf)
+            .map_err(|e| InternalError::from(e).at(xml.buffer_position()))?
+        {
+            Event::Start(e) if e.local_name().as_ref() == b"Track" => {
+                current_points.clear();
+            }
+
+            Event::End(e) if e.local_name().as_ref() == b"Track" && !current_points.is_empty() => {
+                segments.push(Segment::new(std::mem::take(&mut current_points)));
+            }
+
+            Event::Start(e) if e.local_name().as_ref() == b"Trackpoint" => {
+                current = Some(TcxPoint::default());
+                handler = None;
+            }
+
+            Event::End(e) if e.local_name().as_ref() == b"Trackpoint" => {
+                if let Some(pt) = current.take().map(TcxPoint::finish).transpose()?.flatten() {
+                    current_points.push(pt);
+                }
+                handler = None;
+            }
+
+            Event::Start(e) if current.is_some() => {
+                handler = match e.local_name().as_ref() {
+                    b"HeartRateBpm" => {
+                        in_hr = true;
+                        None
+                    }
+                    b"Value" if in_hr => Some(apply_hr),
+                    tag => find_handler(tag),
+                };
+            }
+
+            Event::Text(e) => {
+                if let (Some(tp), Some(apply)) = (current.as_mut(), handler) {
+                    apply(tp, &read_text_string(e)?)?;
+                }
+            }
+
+            Event::End(e) => {
+                if e.local_name().as_ref() == b"HeartRateBpm" {
+                    in_hr = false;
+                }
+                handler = None;
+            }
+
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(Track::new(segments))
+}
+
+/// Serializes `track` as a TCX 2.0 document holding one activity, with one
+/// `<Lap>` per segment. Heart rate and cadence are written when present.
+/// TCX requires at least one lap and a `<Time>` on every trackpoint, so a
+/// track without segments or with an untimed point is rejected with
+/// [`Error::InvalidData`] before anything is written.
+pub fn write_tcx<W: Write>(track: &Track, writer: W) -> Result<(), Error> {
+    if track.segments().is_empty() {
+        return Err(Error::InvalidData {
+            message: "TCX needs at least one segment.".into(),
+        });
+    }
+    if track.points().any(|pt| pt.time.is_none()) {
+        return Err(Error::InvalidData {
+            message: "TCX needs a time on every trackpoint.".into(),
+        });
+    }
+    write_database(track, &mut Writer::new_with_indent(writer, b' ', 2)).map_err(Error::from)
+}
+
+fn write_database<W: Write>(track: &Track, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    xml.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    let mut root = BytesStart::new("TrainingCenterDatabase");
+    root.push_attribute(("xmlns", TCX_NS));
+    xml.write_event(Event::Start(root))?;
+    xml.write_event(Event::Start(BytesStart::new("Activities")))?;
+
+    let mut activity = BytesStart::new("Activity");
+    activity.push_attribute(("Sport", "Other"));
+    xml.write_event(Event::Start(activity))?;
+    let id = track.points().find_map(|pt| pt.time.as_deref());
+    write_text(xml, "Id", id.unwrap_or(EPOCH))?;
+
+    for seg in track.segments() {
+        write_lap(seg, xml)?;
+    }
+
+    xml.write_event(Event::End(BytesEnd::new("Activity")))?;
+    xml.write_event(Event::End(BytesEnd::new("Activities")))?;
+    xml.write_event(Event::End(BytesEnd::new("TrainingCenterDatabase")))?;
+    xml.get_mut().flush()?;
+    Ok(())
+}
+
+fn write_lap<W: Write>(seg: &Segment, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    let times: Vec<_> = seg
+        .points()
+        .iter()
+        .filter_map(|pt| pt.timestamp())
+        .collect();
+    let start = seg.points().iter().find_map(|pt| pt.time.as_deref());
+    let total_secs = match (times.first(), times.last()) {
+        (Some(t0), Some(t1)) => (*t1 - *t0).num_milliseconds() as f64 / 1000.0,
+        _ => 0.0,
+    };
+
+    let mut lap = BytesStart::new("Lap");
+    lap.push_attribute(("StartTime", start.unwrap_or(EPOCH)));
+    xml.write_event(Event::Start(lap))?;
+    write_text(xml, "TotalTimeSeconds", &total_secs.to_string())?;
+    write_text(xml, "DistanceMeters", &seg.total_distance_m().to_string())?;
+    write_text(xml, "Calories", "0")?;
+    write_text(xml, "Intensity", "Active")?;
+    write_text(xml, "TriggerMethod", "Manual")?;
+
+    xml.write_event(Event::Start(BytesStart::new("Track")))?;
+    for pt in seg.points() {
+        write_trackpoint(pt, xml)?;
+    }
+    xml.write_event(Event::End(BytesEnd::new("Track")))?;
+
+    xml.write_event(Event::End(BytesEnd::new("Lap")))?;
+    Ok(())
+}
+
+fn write_trackpoint<W: Write>(pt: &TrackPoint, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    xml.write_event(Event::Start(BytesStart::new("Trackpoint")))?;
+
+    if let Some(time) = &pt.time {
+        write_text(xml, "Time", time)?;
+    }
+    xml.write_event(Event::Start(BytesStart::new("Position")))?;
+    write_text(xml, "LatitudeDegrees", &pt.lat.to_string())?;
+    write_text(xml, "LongitudeDegrees", &pt.lon.to_string())?;
+    xml.write_event(Event::End(BytesEnd::new("Position")))?;
+    if let Some(ele) = pt.ele {
+        write_text(xml, "AltitudeMeters", &ele.to_string())?;
+    }
+    if let Some(hr) = pt.hr {
+        xml.write_event(Event::Start(BytesStart::new("HeartRateBpm")))?;
+        write_text(xml, "Value", &hr.to_string())?;
+        xml.write_event(Event::End(BytesEnd::new("HeartRateBpm")))?;
+    }
+    if let Some(cadence) = pt.cadence {
+        write_text(xml, "Cadence", &cadence.to_string())?;
+    }
+
+    xml.write_event(Event::End(BytesEnd::new("Trackpoint")))?;
+    Ok(())
+}
+
+#[test]
+fn parse_tcx_matches_gpx() {
+    let tcx = r#"<?xml version="1.0" encoding="UTF-8"?>
+    <TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2">
+      <Activities>
+        <Activity Sport="Biking">
+          <Id>2024-01-01T10:00:00Z</Id>
+          <Lap StartTime="2024-01-01T10:00:00Z">
+            <TotalTimeSeconds>20</TotalTimeSeconds>
+            <Cadence>85</Cadence>
+            <Track>
+              <Trackpoint>
+                <Time>2024-01-01T10:00:00Z</Time>
+                <Position><LatitudeDegrees>50.8755</LatitudeDegrees><LongitudeDegrees>-1.2826</LongitudeDegrees></Position>
+                <AltitudeMeters>46.1</AltitudeMeters>
+                <HeartRateBpm><Value>128</Value></HeartRateBpm>
+                <Cadence>84</Cadence>
+              </Trackpoint>
+              <Trackpoint>
+                <Time>2024-01-01T10:00:05Z</Time>
+                <HeartRateBpm><Value>129</Value></HeartRateBpm>
+              </Trackpoint>
+              <Trackpoint>
+                <Time>2024-01-01T10:00:10Z</Time>
+                <Position><LatitudeDegrees>50.8750</LatitudeDegrees><LongitudeDegrees>-1.2820</LongitudeDegrees></Position>
+                <AltitudeMeters>47.9</AltitudeMeters>
+                <HeartRateBpm><Value>131</Value></HeartRateBpm>
+              </Trackpoint>
+            </Track>
+          </Lap>
+        </Activity>
+      </Activities>
+    </TrainingCenterDatabase>"#;
+    let gpx = r#"<gpx><trk><trkseg>
+      <trkpt lat="50.8755" lon="-1.2826"><ele>46.1</ele><time>2024-01-01T10:00:00Z</time>
+        <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>128</gpxtpx:hr><gpxtpx:cad>84</gpxtpx:cad></gpxtpx:TrackPointExtension></extensions>
+      </trkpt>
+      <trkpt lat="50.8750" lon="-1.2820"><ele>47.9</ele><time>2024-01-01T10:00:10Z</time>
+        <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>131</gpxtpx:hr></gpxtpx:TrackPointExtension></extensions>
+      </trkpt>
+    </trkseg></trk></gpx>"#;
+
+    let from_tcx = parse_tcx(std::io::Cursor::new(tcx)).unwrap();
+    let from_gpx = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+
+    assert_eq!(from_tcx.segment_count(), 1);
+    assert_eq!(from_tcx.segments, from_gpx.segments);
+}
+
+#[test]
+fn write_tcx_round_trip() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="50.87551" lon="-1.28259"><ele>46.164</ele><time>2024-01-01T10:00:00Z</time>
+                <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>131</gpxtpx:hr><gpxtpx:cad>90</gpxtpx:cad></gpxtpx:TrackPointExtension></extensions>
+              </trkpt>
+              <trkpt lat="50.87545" lon="-1.28237"><ele>46.848</ele><time>2024-01-01T10:00:30Z</time></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="50.87533" lon="-1.28199"><time>2024-01-01T10:05:00Z</time></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let mut out = Vec::new();
+    write_tcx(&track, &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let back = parse_tcx(std::io::Cursor::new(&text)).unwrap();
+
+    assert!(text.contains("<Id>2024-01-01T10:00:00Z</Id>"));
+    assert!(text.contains("<TotalTimeSeconds>30</TotalTimeSeconds>"));
+    assert_eq!(text.matches("<Lap ").count(), 2);
+    assert_eq!(back.segment_count(), 2);
+    for (a, b) in back.points().zip(track.points()) {
+        assert!((a.lat - b.lat).abs() < 1e-12);
+        assert!((a.lon - b.lon).abs() < 1e-12);
+        assert_eq!(a.ele, b.ele);
+        assert_eq!(a.time, b.time);
+        assert_eq!((a.hr, a.cadence), (b.hr, b.cadence));
+    }
+}
+
+#[test]
+fn write_tcx_rejects_untimed_points_and_empty_tracks() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk><trkseg>
+            <trkpt lat="50.0" lon="-1.0"><time>2024-01-01T10:00:00Z</time></trkpt>
+            <trkpt lat="50.0" lon="-1.001"></trkpt>
+        </trkseg></trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let mut out = Vec::new();
+    let err = write_tcx(&track, &mut out).unwrap_err();
+    assert!(matches!(err, Error::InvalidData { .. }), "{err:?}");
+    assert!(out.is_empty());
+
+    let err = write_tcx(&Track::new(Vec::new()), &mut out).unwrap_err();
+    assert!(matches!(err, Error::InvalidData { .. }), "{err:?}");
+}
@@ -1,23 +1,116 @@
-use crate::gpx::segment::Segment;
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+    time::Duration,
+};
 
-#[derive(Debug)]
+use chrono::{DateTime, FixedOffset};
+
+use crate::gpx::{
+    BoundingBox, Error, Metadata, NormalizeOptions, TrackDiff, TrackPoint, TrackStats, Waypoint,
+    diff,
+    err::InternalError,
+    parse_track,
+    segment::{
+        DistanceFormula, ElevationSample, GradeSample, PaceSample, Segment, haversine_m,
+        pace_min_per_km,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Track {
     pub segments: Vec<Segment>,
+    pub waypoints: Vec<Waypoint>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub metadata: Option<Metadata>,
+    /// The `creator` attribute of the root `<gpx>` element.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub creator: Option<String>,
+    /// The `version` attribute of the root `<gpx>` element.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub version: Option<String>,
+    /// The Garmin `DisplayColor` track extension, e.g. `"Red"`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub display_color: Option<String>,
 }
 
 impl Track {
     pub fn new(segment: Vec<Segment>) -> Self {
-        Self { segments: segment }
+        Self {
+            segments: segment,
+            waypoints: Vec::new(),
+            metadata: None,
+            creator: None,
+            version: None,
+            display_color: None,
+        }
+    }
+
+    /// Opens the file at `path` and parses it as a GPX track. With the
+    /// `flate2` feature, files ending in `.gz` are decompressed first.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Track, Error> {
+        let path = path.as_ref();
+        let reader = BufReader::new(File::open(path).map_err(InternalError::from)?);
+
+        #[cfg(feature = "flate2")]
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+        {
+            return crate::gpx::parse_track_gz(reader);
+        }
+
+        parse_track(reader)
     }
 
     pub fn segments(&self) -> &[Segment] {
         &self.segments
     }
 
+    /// Iterates over every point of every segment, in order.
+    pub fn points(&self) -> impl Iterator<Item = &TrackPoint> {
+        self.into_iter()
+    }
+
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    /// The file's `<metadata>` block, if it has one.
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    /// The application that wrote the file, e.g. `"StravaGPX"`.
+    pub fn creator(&self) -> Option<&str> {
+        self.creator.as_deref()
+    }
+
+    /// The GPX schema version declared by the file, e.g. `"1.1"`.
+    pub fn gpx_version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// The colour Garmin tools draw the track in, from the
+    /// `<gpxx:DisplayColor>` track extension.
+    pub fn display_color(&self) -> Option<&str> {
+        self.display_color.as_deref()
+    }
+
     pub fn total_distance_m(&self) -> f64 {
         self.segments.iter().map(|s| s.total_distance_m()).sum()
     }
 
+    /// Sum of [`Segment::total_distance_with`] over all segments.
+    pub fn total_distance_with(&self, formula: DistanceFormula) -> f64 {
+        self.segments
+            .iter()
+            .map(|s| s.total_distance_with(formula))
+            .sum()
+    }
+
     pub fn total_ascent_descent_m(&self) -> (f64, f64) {
         let mut ascent = 0.0;
         let mut descent = 0.0;
@@ -31,7 +124,1677 @@ impl Track {
         (ascent, descent)
     }
 
+    /// Whether any point of the track carries an elevation.
+    pub fn has_elevation_data(&self) -> bool {
+        self.points().any(|p| p.ele.is_some())
+    }
+
+    /// Like [`Track::total_ascent_descent_m`], but `None` when no point has
+    /// an elevation, so that a track without elevation data can be told
+    /// apart from a flat one.
+    pub fn checked_ascent_descent_m(&self) -> Option<(f64, f64)> {
+        self.has_elevation_data()
+            .then(|| self.total_ascent_descent_m())
+    }
+
     pub fn segment_count(&self) -> usize {
         self.segments.len()
     }
+
+    /// The number of segments, matching `track[i]` indexing.
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Whether the track has no segments.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn total_point_count(&self) -> usize {
+        self.segments.iter().map(|s| s.point_count()).sum()
+    }
+
+    pub fn max_elevation(&self) -> Option<f64> {
+        self.segments
+            .iter()
+            .filter_map(|s| s.max_elevation())
+            .reduce(f64::max)
+    }
+
+    pub fn min_elevation(&self) -> Option<f64> {
+        self.segments
+            .iter()
+            .filter_map(|s| s.min_elevation())
+            .reduce(f64::min)
+    }
+
+    /// Returns `(min, max)` elevation over the points that have one.
+    pub fn elevation_range(&self) -> Option<(f64, f64)> {
+        self.min_elevation().zip(self.max_elevation())
+    }
+
+    /// Returns the elevation of the last point that has one minus that of
+    /// the first, i.e. how much higher the track ends than it starts.
+    pub fn net_elevation_change(&self) -> Option<f64> {
+        let first = self.points().find_map(|p| p.ele)?;
+        let last = self
+            .segments
+            .iter()
+            .rev()
+            .find_map(|s| s.points().iter().rev().find_map(|p| p.ele))?;
+        Some(last - first)
+    }
+
+    /// Returns the smallest box holding every point, or `None` if the track
+    /// has no points. Tracks crossing the antimeridian are not handled.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::enclosing(self.points().map(|p| (p.lat, p.lon)))
+    }
+
+    /// Returns the `(lat, lon)` midpoint of the track's bounding box, for
+    /// centring a map on it. Unlike a mean of the points, this is not pulled
+    /// towards places where many points were recorded. Tracks crossing the
+    /// antimeridian are not handled. `None` if the track has no points.
+    pub fn center(&self) -> Option<(f64, f64)> {
+        self.bounding_box().map(|bbox| bbox.center())
+    }
+
+    /// Returns the `(lat, lon)` mean position of the track's points, e.g.
+    /// for clustering tracks. The points are averaged as unit vectors in 3D
+    /// and the mean projected back onto the sphere, so tracks crossing the
+    /// antimeridian come out right. This is a Cartesian mean on the sphere,
+    /// not a true geodetic centroid. `None` if the track has no points.
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        let (x, y, z, n) = self
+            .points()
+            .fold((0.0, 0.0, 0.0, 0usize), |(x, y, z, n), pt| {
+                let (lat, lon) = (pt.lat.to_radians(), pt.lon.to_radians());
+                (
+                    x + lat.cos() * lon.cos(),
+                    y + lat.cos() * lon.sin(),
+                    z + lat.sin(),
+                    n + 1,
+                )
+            });
+        (n > 0).then(|| {
+            let lat = z.atan2(x.hypot(y));
+            let lon = y.atan2(x);
+            (lat.to_degrees(), lon.to_degrees())
+        })
+    }
+
+    /// Returns the straight-line distance between the last point of each
+    /// segment and the first point of the next one.
+    pub fn segment_gaps_m(&self) -> Vec<f64> {
+        self.segments
+            .iter()
+            .filter_map(|s| Some((s.first()?, s.last()?)))
+            .collect::<Vec<_>>()
+            .windows(2)
+            .map(|w| haversine_m(w[0].1, w[1].0))
+            .collect()
+    }
+
+    /// Returns the first and last point of each segment, e.g. for placing
+    /// start/finish markers per lap. Empty segments yield `(None, None)`.
+    pub fn segment_endpoints(&self) -> Vec<(Option<TrackPoint>, Option<TrackPoint>)> {
+        self.segments
+            .iter()
+            .map(|s| (s.first().cloned(), s.last().cloned()))
+            .collect()
+    }
+
+    /// Estimates the distance travelled while not recording, as the sum of
+    /// [`Track::segment_gaps_m`].
+    pub fn estimated_unrecorded_distance_m(&self) -> f64 {
+        self.segment_gaps_m().iter().sum()
+    }
+
+    /// Compares this track (the old version) against `other`, aligning the
+    /// flattened point sequences. Points within `tolerance_m` of their
+    /// counterpart are reported as unchanged (identical coordinates) or
+    /// moved; the rest as added or removed.
+    pub fn diff(&self, other: &Track, tolerance_m: f64) -> TrackDiff {
+        let old: Vec<&TrackPoint> = self.points().collect();
+        let new: Vec<&TrackPoint> = other.points().collect();
+        diff::align(&old, &new, tolerance_m)
+    }
+
+    /// Writes one CSV row per point with the header
+    /// `segment,index,lat,lon,ele,time,hr,cad`. Missing values are left empty.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let opt = |v: Option<String>| v.unwrap_or_default();
+
+        writeln!(writer, "segment,index,lat,lon,ele,time,hr,cad").map_err(InternalError::from)?;
+        for (s, seg) in self.segments.iter().enumerate() {
+            for (i, pt) in seg.points().iter().enumerate() {
+                writeln!(
+                    writer,
+                    "{s},{i},{},{},{},{},{},{}",
+                    pt.lat,
+                    pt.lon,
+                    opt(pt.ele.map(|v| v.to_string())),
+                    opt(pt.time.clone()),
+                    opt(pt.hr.map(|v| v.to_string())),
+                    opt(pt.cadence.map(|v| v.to_string())),
+                )
+                .map_err(InternalError::from)?;
+            }
+        }
+        writer.flush().map_err(InternalError::from)?;
+        Ok(())
+    }
+
+    /// Summarises the track in a single [`TrackStats`], walking the points
+    /// once. Duration runs from the first to the last timestamped point.
+    pub fn stats(&self) -> TrackStats {
+        let mut stats = TrackStats {
+            segment_count: self.segment_count(),
+            point_count: 0,
+            distance_m: 0.0,
+            ascent_m: 0.0,
+            descent_m: 0.0,
+            duration: None,
+            avg_speed_kmh: None,
+        };
+        let mut first_time = None;
+        let mut last_time = None;
+
+        for seg in &self.segments {
+            let mut prev: Option<&TrackPoint> = None;
+            for pt in seg.points() {
+                stats.point_count += 1;
+                if let Some(t) = pt.timestamp() {
+                    first_time.get_or_insert(t);
+                    last_time = Some(t);
+                }
+                if let Some(prev) = prev {
+                    stats.distance_m += haversine_m(prev, pt);
+                    if let (Some(e1), Some(e2)) = (prev.ele, pt.ele) {
+                        let delta = e2 - e1;
+                        if delta > 0.0 {
+                            stats.ascent_m += delta;
+                        } else {
+                            stats.descent_m -= delta;
+                        }
+                    }
+                }
+                prev = Some(pt);
+            }
+        }
+
+        stats.set_timing(first_time, last_time);
+        stats
+    }
+
+    /// Like [`Track::total_distance_m`], but sums the segments on the rayon
+    /// thread pool. The per-segment totals are added in order, so the result
+    /// is bitwise-equal to the sequential one.
+    #[cfg(feature = "rayon")]
+    pub fn total_distance_par_m(&self) -> f64 {
+        use rayon::prelude::*;
+
+        let per_segment: Vec<f64> = self
+            .segments
+            .par_iter()
+            .map(Segment::total_distance_m)
+            .collect();
+        per_segment.iter().sum()
+    }
+
+    /// Like [`Track::stats`], but computes each segment's distance and
+    /// ascent/descent on the rayon thread pool. Totals match
+    /// [`Track::total_distance_m`] and [`Track::total_ascent_descent_m`]
+    /// exactly; they may differ from [`Track::stats`] in the last bits.
+    #[cfg(feature = "rayon")]
+    pub fn stats_par(&self) -> TrackStats {
+        use rayon::prelude::*;
+
+        let per_segment: Vec<(f64, (f64, f64))> = self
+            .segments
+            .par_iter()
+            .map(|s| (s.total_distance_m(), s.total_ascent_descent_m()))
+            .collect();
+
+        let mut stats = TrackStats {
+            segment_count: self.segment_count(),
+            point_count: self.total_point_count(),
+            distance_m: per_segment.iter().map(|(d, _)| d).sum(),
+            ascent_m: per_segment.iter().map(|(_, (a, _))| a).sum(),
+            descent_m: per_segment.iter().map(|(_, (_, d))| d).sum(),
+            duration: None,
+            avg_speed_kmh: None,
+        };
+        let first_time = self.points().find_map(TrackPoint::timestamp);
+        let last_time = self
+            .segments
+            .iter()
+            .rev()
+            .flat_map(|s| s.points().iter().rev())
+            .find_map(TrackPoint::timestamp);
+        stats.set_timing(first_time, last_time);
+        stats
+    }
+
+    /// Returns the time taken between cumulative distances `d0` and `d1`,
+    /// interpolating timestamps within the legs that contain them. Returns
+    /// `None` if either distance is outside the track, `d1 < d0`, or the
+    /// surrounding points have no timestamps.
+    pub fn duration_between_distances_m(&self, d0: f64, d1: f64) -> Option<Duration> {
+        let t0 = self.time_at_distance_m(d0)?;
+        let t1 = self.time_at_distance_m(d1)?;
+        (t1 - t0).to_std().ok()
+    }
+
+    fn time_at_distance_m(&self, distance_m: f64) -> Option<DateTime<FixedOffset>> {
+        self.point_at_distance(distance_m)?.timestamp()
+    }
+
+    /// Returns the interpolated point `distance_m` metres along the track,
+    /// measured over the segments only (gaps between segments are not
+    /// counted). See [`Segment::point_at_distance`].
+    pub fn point_at_distance(&self, distance_m: f64) -> Option<TrackPoint> {
+        if distance_m < 0.0 {
+            return None;
+        }
+
+        let mut remaining = distance_m;
+        for seg in &self.segments {
+            let len = seg.total_distance_m();
+            if remaining <= len {
+                return seg.point_at_distance(remaining);
+            }
+            remaining -= len;
+        }
+        None
+    }
+
+    /// Returns the point at time `at`, interpolated within the segment
+    /// that covers it (see [`Segment::point_at_time`]). `None` if `at`
+    /// falls outside every segment, including the gaps between them.
+    pub fn point_at_time(&self, at: DateTime<FixedOffset>) -> Option<TrackPoint> {
+        self.segments.iter().find_map(|seg| seg.point_at_time(at))
+    }
+
+    /// Checks that timestamps never decrease across the track, returning
+    /// the index (into [`Track::points`]) of the first point whose time is
+    /// earlier than a previous one. Points without a timestamp are skipped
+    /// and equal timestamps are allowed. Parsing does not run this check.
+    pub fn validate_monotonic_time(&self) -> Result<(), usize> {
+        let mut latest: Option<DateTime<FixedOffset>> = None;
+        for (i, pt) in self.points().enumerate() {
+            let Some(t) = pt.timestamp() else {
+                continue;
+            };
+            if latest.is_some_and(|latest| t < latest) {
+                return Err(i);
+            }
+            latest = Some(t);
+        }
+        Ok(())
+    }
+
+    /// Returns `(segment_idx, point_idx, distance_m)` for the point closest
+    /// to `(lat, lon)`, e.g. for a click on a map. `None` if the track has no
+    /// points.
+    pub fn nearest_point(&self, lat: f64, lon: f64) -> Option<(usize, usize, f64)> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter_map(|(s, seg)| {
+                let (p, d) = seg.nearest_point(lat, lon)?;
+                Some((s, p, d))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+    }
+
+    /// Snaps `(lat, lon)` onto the track's line, e.g. for an "am I on
+    /// route?" check. Returns `(segment_idx, point_idx, distance_m, point)`
+    /// where `point_idx` is the first point of the closest leg; see
+    /// [`Segment::nearest_point_on_line`]. Gaps between segments are not
+    /// part of the line. `None` if the track has no points.
+    pub fn nearest_point_on_line(
+        &self,
+        lat: f64,
+        lon: f64,
+    ) -> Option<(usize, usize, f64, TrackPoint)> {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter_map(|(s, seg)| {
+                let (p, d, pt) = seg.nearest_point_on_line(lat, lon)?;
+                Some((s, p, d, pt))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+    }
+
+    /// [`Segment::bearing_at`] for point `pt` of segment `seg`.
+    pub fn bearing_at(&self, seg: usize, pt: usize) -> Option<f64> {
+        self.segments.get(seg)?.bearing_at(pt)
+    }
+
+    /// Finds places where the track comes back within `radius_m` of itself,
+    /// e.g. a crossing or a return to the start, as
+    /// `(seg1, pt1, seg2, pt2)` with the first point earlier in the track.
+    /// Runs of neighbouring matches are one place and are reported once, as
+    /// their closest pair. Points less than `2 * radius_m` apart along the
+    /// track are never matched. This compares every pair of points.
+    pub fn detect_loops(&self, radius_m: f64) -> Vec<(usize, usize, usize, usize)> {
+        let points: Vec<_> = self
+            .segments
+            .iter()
+            .enumerate()
+            .flat_map(|(s, seg)| {
+                seg.points()
+                    .iter()
+                    .enumerate()
+                    .map(move |(p, pt)| (s, p, pt))
+            })
+            .collect();
+        let mut along = Vec::with_capacity(points.len());
+        let mut travelled = 0.0;
+        for (i, &(_, _, pt)) in points.iter().enumerate() {
+            if i > 0 {
+                travelled += haversine_m(points[i - 1].2, pt);
+            }
+            along.push(travelled);
+        }
+
+        // Matches grouped with union-find; a match joins any earlier match
+        // one step away on both sides.
+        let mut matches: Vec<(usize, usize, f64)> = Vec::new();
+        let mut index = std::collections::HashMap::new();
+        let mut parent: Vec<usize> = Vec::new();
+        fn root(parent: &mut [usize], mut m: usize) -> usize {
+            while parent[m] != m {
+                parent[m] = parent[parent[m]];
+                m = parent[m];
+            }
+            m
+        }
+        for i in 0..points.len() {
+            for j in i + 1..points.len() {
+                if along[j] - along[i] < 2.0 * radius_m {
+                    continue;
+                }
+                let d = haversine_m(points[i].2, points[j].2);
+                if d > radius_m {
+                    continue;
+                }
+                let m = matches.len();
+                matches.push((i, j, d));
+                parent.push(m);
+                index.insert((i, j), m);
+                for (di, nj) in [(1, j - 1), (1, j), (1, j + 1), (0, j - 1)] {
+                    let Some(&other) = i.checked_sub(di).and_then(|ni| index.get(&(ni, nj))) else {
+                        continue;
+                    };
+                    let (a, b) = (root(&mut parent, other), root(&mut parent, m));
+                    parent[b] = a;
+                }
+            }
+        }
+
+        let mut best: std::collections::BTreeMap<usize, (usize, usize, f64)> = Default::default();
+        for (m, &candidate) in matches.iter().enumerate() {
+            let r = root(&mut parent, m);
+            best.entry(r)
+                .and_modify(|b| {
+                    if candidate.2 < b.2 {
+                        *b = candidate;
+                    }
+                })
+                .or_insert(candidate);
+        }
+        let mut loops: Vec<_> = best
+            .into_values()
+            .map(|(i, j, _)| (points[i].0, points[i].1, points[j].0, points[j].1))
+            .collect();
+        loops.sort_unstable();
+        loops
+    }
+
+    /// Returns the part of the track between cumulative distances `start_m`
+    /// and `end_m`, measured as in [`Track::point_at_distance`]. Boundary
+    /// points are interpolated at exactly those distances. Returns a track
+    /// without segments if `start_m >= end_m` or the range is not within
+    /// the track.
+    pub fn crop_by_distance(&self, start_m: f64, end_m: f64) -> Track {
+        if start_m < 0.0 || start_m >= end_m || end_m > self.total_distance_m() {
+            return self.with_segments(Vec::new());
+        }
+
+        let mut segments = Vec::new();
+        let mut offset = 0.0;
+        for seg in &self.segments {
+            let len = seg.total_distance_m();
+            let lo = (start_m - offset).max(0.0);
+            let hi = (end_m - offset).min(len);
+            if lo < hi {
+                segments.push(seg.crop_by_distance(lo, hi));
+            }
+            offset += len;
+        }
+        self.with_segments(segments)
+    }
+
+    /// Returns the part of the track recorded between `start` and `end`, with
+    /// boundary points interpolated at exactly those times (see
+    /// [`Segment::crop_by_time`]). Segments left empty are dropped. Fails
+    /// with [`Error::InvalidData`] if no point has a timestamp.
+    pub fn crop_by_time(
+        &self,
+        start: DateTime<FixedOffset>,
+        end: DateTime<FixedOffset>,
+    ) -> Result<Track, Error> {
+        if self.points().all(|pt| pt.timestamp().is_none()) {
+            return Err(Error::InvalidData {
+                message: "track has no timestamps.".into(),
+            });
+        }
+
+        let segments = self
+            .segments
+            .iter()
+            .map(|seg| seg.crop_by_time(start, end))
+            .filter(|seg| seg.point_count() > 0)
+            .collect();
+        Ok(self.with_segments(segments))
+    }
+
+    /// Concatenates the elevation profiles of all segments, carrying the
+    /// cumulative distance across segment boundaries.
+    pub fn elevation_profile(&self) -> Vec<ElevationSample> {
+        let mut offset_m = 0.0;
+        let mut samples = Vec::new();
+
+        for seg in &self.segments {
+            samples.extend(
+                seg.elevation_profile()
+                    .into_iter()
+                    .map(|s| ElevationSample {
+                        distance_m: s.distance_m + offset_m,
+                        ..s
+                    }),
+            );
+            offset_m += seg.total_distance_m();
+        }
+        samples
+    }
+
+    /// Concatenates the grade profiles of all segments, carrying the
+    /// cumulative distance across segment boundaries.
+    pub fn grade_profile(&self) -> Vec<GradeSample> {
+        let mut offset_m = 0.0;
+        let mut samples = Vec::new();
+
+        for seg in &self.segments {
+            samples.extend(seg.grade_profile().into_iter().map(|s| GradeSample {
+                distance_m: s.distance_m + offset_m,
+                ..s
+            }));
+            offset_m += seg.total_distance_m();
+        }
+        samples
+    }
+
+    /// Concatenates the pace profiles of all segments, carrying the
+    /// cumulative distance across segment boundaries.
+    pub fn pace_profile(&self) -> Vec<PaceSample> {
+        let mut offset_m = 0.0;
+        let mut samples = Vec::new();
+
+        for seg in &self.segments {
+            samples.extend(seg.pace_profile().into_iter().map(|s| PaceSample {
+                distance_m: s.distance_m + offset_m,
+                ..s
+            }));
+            offset_m += seg.total_distance_m();
+        }
+        samples
+    }
+
+    /// Returns the time spent in each heart-rate zone, given as inclusive
+    /// `(min_bpm, max_bpm)` ranges. Each point counts for the time until the
+    /// next point of its segment, towards every zone its `hr` falls in.
+    /// Returns an empty vector if no point has heart-rate data.
+    pub fn hr_zone_time(&self, zones: &[(u16, u16)]) -> Vec<Duration> {
+        if self.points().all(|p| p.hr.is_none()) {
+            return Vec::new();
+        }
+
+        let mut totals = vec![Duration::ZERO; zones.len()];
+        for seg in &self.segments {
+            for w in seg.points().windows(2) {
+                let Some(hr) = w[0].hr.map(u16::from) else {
+                    continue;
+                };
+                let Some(dt) = w[0]
+                    .timestamp()
+                    .zip(w[1].timestamp())
+                    .and_then(|(t0, t1)| (t1 - t0).to_std().ok())
+                else {
+                    continue;
+                };
+                for (total, &(min, max)) in totals.iter_mut().zip(zones) {
+                    if (min..=max).contains(&hr) {
+                        *total += dt;
+                    }
+                }
+            }
+        }
+        totals
+    }
+
+    /// Returns the pace in minutes per kilometre over the track's distance
+    /// and its duration from the first to the last timestamp, pauses
+    /// between segments included. `None` without two distinct timestamps or
+    /// when the distance is zero.
+    pub fn avg_pace_min_per_km(&self) -> Option<f64> {
+        let stats = self.stats();
+        let ms = stats.duration?.as_millis().try_into().ok()?;
+        pace_min_per_km(stats.distance_m, ms)
+    }
+
+    /// Applies [`Segment::smooth_elevation`] to every segment.
+    pub fn smooth_elevation(&self, window: usize) -> Track {
+        self.with_segments(
+            self.segments
+                .iter()
+                .map(|s| s.smooth_elevation(window))
+                .collect(),
+        )
+    }
+
+    /// Applies [`Segment::filter_points`] to every segment, dropping the
+    /// segments left without points.
+    pub fn filter_points<F: Fn(&TrackPoint) -> bool>(&self, predicate: F) -> Track {
+        self.with_segments(
+            self.segments
+                .iter()
+                .map(|s| s.filter_points(&predicate))
+                .filter(|s| !s.is_empty())
+                .collect(),
+        )
+    }
+
+    /// Keeps only the points inside `bbox` (edges included). Each stretch
+    /// of a segment inside the box becomes its own segment, so a track that
+    /// leaves the box and comes back is split there.
+    pub fn clip_to_bbox(&self, bbox: BoundingBox) -> Track {
+        let mut segments = Vec::new();
+        for seg in &self.segments {
+            let mut inside: Vec<TrackPoint> = Vec::new();
+            for pt in seg.points() {
+                if bbox.contains(pt.lat, pt.lon) {
+                    inside.push(pt.clone());
+                } else if !inside.is_empty() {
+                    segments.push(Segment::new(std::mem::take(&mut inside)));
+                }
+            }
+            if !inside.is_empty() {
+                segments.push(Segment::new(inside));
+            }
+        }
+        self.with_segments(segments)
+    }
+
+    /// Applies [`Segment::resample_by_time`] to every segment, giving one
+    /// point per `interval` of recording time. Segments without timestamps
+    /// are kept unchanged, as is the whole track if `interval` is zero.
+    pub fn resample_time(&self, interval: Duration) -> Track {
+        self.with_segments(
+            self.segments
+                .iter()
+                .map(|s| s.resample_by_time(interval).unwrap_or_else(|_| s.clone()))
+                .collect(),
+        )
+    }
+
+    /// Applies the cleanups enabled in `opts` to every segment, in this
+    /// order: [`Segment::deduplicate_consecutive`],
+    /// [`Segment::remove_speed_outliers`], [`Segment::smooth_elevation`] and
+    /// [`Segment::split_by_time_gap`]. Segments left empty are dropped.
+    pub fn normalize(&self, opts: NormalizeOptions) -> Track {
+        let mut segments = Vec::new();
+
+        for seg in &self.segments {
+            let mut seg = seg.clone();
+            if let Some(threshold) = opts.dedup_threshold_m {
+                seg = seg.deduplicate_consecutive(threshold);
+            }
+            if let Some(max_speed) = opts.max_speed_kmh {
+                seg = seg.remove_speed_outliers(max_speed);
+            }
+            if let Some(window) = opts.smooth_window {
+                seg = seg.smooth_elevation(window);
+            }
+            match opts.split_gap {
+                Some(gap) => segments.extend(seg.split_by_time_gap(gap)),
+                None => segments.push(seg),
+            }
+        }
+        segments.retain(|s| s.point_count() > 0);
+
+        self.with_segments(segments)
+    }
+
+    /// Splits the track into separate activities wherever two consecutive
+    /// timestamped points, within a segment or across a segment boundary,
+    /// are more than `gap` apart. Segment boundaries are kept inside each
+    /// part, and a gap inside a segment splits that segment (see
+    /// [`Segment::split_by_time_gap`]). Every part keeps the waypoints and
+    /// other track-level data. A track without points yields no parts.
+    pub fn split_by_time_gap(&self, gap: Duration) -> Vec<Track> {
+        let mut tracks = Vec::new();
+        let mut current: Vec<Segment> = Vec::new();
+        let mut last_time: Option<DateTime<FixedOffset>> = None;
+
+        for seg in &self.segments {
+            for (i, piece) in seg.split_by_time_gap(gap).into_iter().enumerate() {
+                let first_time = piece.points().iter().find_map(|p| p.timestamp());
+                let across = last_time
+                    .zip(first_time)
+                    .and_then(|(t0, t1)| (t1 - t0).to_std().ok())
+                    .is_some_and(|d| d > gap);
+                if (i > 0 || across) && !current.is_empty() {
+                    tracks.push(self.with_segments(std::mem::take(&mut current)));
+                }
+                if let Some(t) = piece.points().iter().rev().find_map(|p| p.timestamp()) {
+                    last_time = Some(t);
+                }
+                current.push(piece);
+            }
+        }
+        if !current.is_empty() {
+            tracks.push(self.with_segments(current));
+        }
+        tracks
+    }
+
+    /// Returns the track traversed backwards: segments in reverse order, each
+    /// with its points reversed. Ascent and descent swap.
+    pub fn reverse(&self) -> Track {
+        self.with_segments(self.segments.iter().rev().map(Segment::reverse).collect())
+    }
+
+    /// Collapses all segments into one, in order. See [`Segment::merge`] for
+    /// how the joining legs count towards distance.
+    pub fn merge_segments(&self) -> Track {
+        let merged = self.segments.iter().cloned().reduce(Segment::merge);
+        self.with_segments(merged.into_iter().collect())
+    }
+
+    /// Encodes every point of the track, across segments, as a Google
+    /// encoded polyline with `precision` decimal places (5 for Google, 6
+    /// for OSRM/Valhalla). See [`polyline::encode`](crate::gpx::polyline::encode).
+    ///
+    /// # Panics
+    ///
+    /// If `precision` is above
+    /// [`polyline::MAX_PRECISION`](crate::gpx::polyline::MAX_PRECISION).
+    pub fn to_polyline(&self, precision: u32) -> String {
+        crate::gpx::polyline::encode_points(self, precision)
+    }
+
+    /// Decodes an encoded polyline into a single-segment track; the inverse
+    /// of [`Track::to_polyline`] up to segment boundaries. Fails with
+    /// [`Error::InvalidData`] for a precision above
+    /// [`polyline::MAX_PRECISION`](crate::gpx::polyline::MAX_PRECISION).
+    pub fn from_polyline(s: &str, precision: u32) -> Result<Track, Error> {
+        let points = crate::gpx::polyline::decode_with_precision(s, precision)?;
+        Ok(Track::new(vec![Segment::new(points)]))
+    }
+
+    /// Returns a track with the same waypoints and other track-level data
+    /// but different segments.
+    fn with_segments(&self, segments: Vec<Segment>) -> Track {
+        Track {
+            segments,
+            waypoints: self.waypoints.clone(),
+            metadata: self.metadata.clone(),
+            creator: self.creator.clone(),
+            version: self.version.clone(),
+            display_color: self.display_color.clone(),
+        }
+    }
+}
+
+impl std::str::FromStr for Track {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::gpx::parse_track_str(s)
+    }
+}
+
+impl std::ops::Index<usize> for Track {
+    type Output = Segment;
+
+    fn index(&self, index: usize) -> &Segment {
+        &self.segments[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a Track {
+    type Item = &'a TrackPoint;
+    type IntoIter = std::iter::FlatMap<
+        std::slice::Iter<'a, Segment>,
+        &'a [TrackPoint],
+        fn(&'a Segment) -> &'a [TrackPoint],
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.iter().flat_map(Segment::points)
+    }
+}
+
+#[test]
+fn from_path_parses_fixture() {
+    let track = Track::from_path("assert/Alt_Portsmouth.gpx").unwrap();
+
+    assert_eq!(track.segment_count(), 1);
+    assert_eq!(track.total_point_count(), track.segments()[0].point_count());
+    assert!(track.total_distance_m() > 0.0);
+}
+
+#[test]
+fn from_path_missing_file_is_input_error() {
+    let err = Track::from_path("assert/does_not_exist.gpx").unwrap_err();
+
+    assert!(matches!(err, Error::Input(_)));
+}
+
+#[test]
+fn point_at_distance_crosses_segments() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"></trkpt>
+              <trkpt lat="0.0" lon="0.001"></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.010"></trkpt>
+              <trkpt lat="0.0" lon="0.012"></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+    let first = track.segments()[0].total_distance_m();
+    let second = track.segments()[1].total_distance_m();
+
+    let pt = track.point_at_distance(first + second / 2.0).unwrap();
+
+    assert!((pt.lon - 0.011).abs() < 1e-9);
+    assert!(track.point_at_distance(first + second + 1.0).is_none());
+}
+
+#[test]
+fn duration_between_distances_first_half() {
+    use crate::gpx::TrackPoint;
+
+    let pts = (0..5)
+        .map(|i| TrackPoint {
+            lat: 0.0,
+            lon: 0.001 * i as f64,
+            ele: None,
+            time: Some(format!("2024-01-01T00:0{i}:00Z")),
+            ..Default::default()
+        })
+        .collect();
+    let track = Track::new(vec![Segment::new(pts)]);
+    let half = track.total_distance_m() / 2.0;
+
+    let d = track.duration_between_distances_m(0.0, half).unwrap();
+    assert_eq!(d, Duration::from_secs(120));
+
+    assert!(
+        track
+            .duration_between_distances_m(0.0, track.total_distance_m() + 1.0)
+            .is_none()
+    );
+}
+
+#[test]
+fn elevation_profile_continues_across_segments() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"><ele>100</ele></trkpt>
+              <trkpt lat="0.0" lon="0.001"><ele>110</ele></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.002"><ele>105</ele></trkpt>
+              <trkpt lat="0.0" lon="0.003"><ele>95</ele></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let profile = track.elevation_profile();
+
+    assert_eq!(profile.len(), 4);
+    assert_eq!(profile[0].distance_m, 0.0);
+    assert_eq!(
+        profile[2].distance_m,
+        track.segments()[0].total_distance_m()
+    );
+    assert!((profile[3].distance_m - track.total_distance_m()).abs() < 1e-9);
+}
+
+#[test]
+fn estimated_unrecorded_distance_between_segments() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"></trkpt>
+              <trkpt lat="0.001" lon="0.0"></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0027987" lon="0.0"></trkpt>
+              <trkpt lat="0.004" lon="0.0"></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    assert_eq!(track.segment_gaps_m().len(), 1);
+    assert!((track.estimated_unrecorded_distance_m() - 200.0).abs() < 0.5);
+}
+
+#[test]
+fn creator_and_version_from_root() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx version="1.1" creator="StravaGPX"><trk><trkseg>
+            <trkpt lat="0.0" lon="0.0"></trkpt>
+        </trkseg></trk></gpx>"#,
+    ))
+    .unwrap();
+
+    assert_eq!(track.creator(), Some("StravaGPX"));
+    assert_eq!(track.gpx_version(), Some("1.1"));
+
+    let bare = crate::gpx::parse_track(std::io::Cursor::new("<gpx></gpx>")).unwrap();
+    assert_eq!(bare.creator(), None);
+    assert_eq!(bare.gpx_version(), None);
+}
+
+#[test]
+fn display_color_from_track_extensions() {
+    let track = Track::from_path("assert/Display_Color.gpx").unwrap();
+
+    assert_eq!(track.display_color(), Some("Red"));
+    assert_eq!(track.len(), 1);
+    assert_eq!(track[0].len(), 2);
+    assert_eq!(track.reverse().display_color(), Some("Red"));
+
+    let plain = Track::from_path("assert/Portsea_Paused.gpx").unwrap();
+    assert_eq!(plain.display_color(), None);
+
+    // A colour on a point is not the track's colour.
+    let point_only = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk><trkseg>
+            <trkpt lat="0.0" lon="0.0"><extensions>
+              <gpxx:TrackPointExtension><gpxx:DisplayColor>Blue</gpxx:DisplayColor></gpxx:TrackPointExtension>
+            </extensions></trkpt>
+        </trkseg></trk></gpx>"#,
+    ))
+    .unwrap();
+    assert_eq!(point_only.display_color(), None);
+}
+
+#[test]
+fn merge_segments_yields_single_segment() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"></trkpt>
+              <trkpt lat="0.0" lon="0.001"></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.003"></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let merged = track.merge_segments();
+
+    assert_eq!(merged.segment_count(), 1);
+    assert!(merged.points().eq(track.points()));
+    let expected = track.total_distance_m() + track.estimated_unrecorded_distance_m();
+    assert!((merged.total_distance_m() - expected).abs() < 1e-9);
+}
+
+#[test]
+fn reverse_reverses_segments_and_points() {
+    let track = Track::from_path("assert/Alt_Portsmouth.gpx").unwrap();
+
+    let reversed = track.reverse();
+
+    assert!(
+        reversed
+            .points()
+            .eq(track.points().collect::<Vec<_>>().into_iter().rev())
+    );
+    let (up, down) = track.total_ascent_descent_m();
+    let (r_up, r_down) = reversed.total_ascent_descent_m();
+    assert!((r_up - down).abs() < 1e-6);
+    assert!((r_down - up).abs() < 1e-6);
+    assert!((reversed.total_distance_m() - track.total_distance_m()).abs() < 1e-6);
+    assert_eq!(reversed.reverse(), track);
+}
+
+#[test]
+fn reverse_keeps_distance_across_segments() {
+    let track = Track::from_path("assert/Portsea_Paused.gpx").unwrap();
+
+    let reversed = track.reverse();
+
+    assert_eq!(reversed[0].len(), track[1].len());
+    assert!((reversed.total_distance_m() - track.total_distance_m()).abs() < 1e-9);
+    assert_eq!(reversed.segment_gaps_m(), track.segment_gaps_m());
+    let (up, down) = track.total_ascent_descent_m();
+    let (r_up, r_down) = reversed.total_ascent_descent_m();
+    assert!((r_up - down).abs() < 1e-9 && (r_down - up).abs() < 1e-9);
+}
+
+#[test]
+fn crop_by_distance_middle_three_km() {
+    // 5 km along the equator in 100 m steps, split into two segments.
+    let step_deg = 100.0 / 111_194.93;
+    let trkpts = |range: std::ops::RangeInclusive<u32>| -> String {
+        range
+            .map(|i| format!(r#"<trkpt lat="0.0" lon="{}"></trkpt>"#, i as f64 * step_deg))
+            .collect()
+    };
+    let gpx = format!(
+        "<gpx><trk><trkseg>{}</trkseg><trkseg>{}</trkseg></trk></gpx>",
+        trkpts(0..=25),
+        trkpts(25..=50)
+    );
+    let track = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+    assert!((track.total_distance_m() - 5000.0).abs() < 1.0);
+
+    let cropped = track.crop_by_distance(1000.0, 4000.0);
+
+    assert_eq!(cropped.segment_count(), 2);
+    assert!((cropped.total_distance_m() - 3000.0).abs() < 1e-6);
+    let first = cropped.points().next().unwrap();
+    assert!((first.lon - 10.0 * step_deg).abs() < 1e-9);
+    assert_eq!(
+        track.crop_by_distance(4000.0, 1000.0).total_point_count(),
+        0
+    );
+    assert_eq!(
+        track.crop_by_distance(1000.0, 6000.0).total_point_count(),
+        0
+    );
+}
+
+#[test]
+fn merge_segments_undoes_split() {
+    let track = Track::from_path("assert/Alt_Portsmouth.gpx").unwrap();
+    let (head, tail) = track.segments()[0].split_at_index(600);
+    let split = track.with_segments(vec![head, tail]);
+    assert_eq!(split.segment_count(), 2);
+
+    let merged = split.merge_segments();
+
+    assert_eq!(merged.segment_count(), 1);
+    assert_eq!(merged.total_point_count(), 1219);
+    assert_eq!(merged, track);
+}
+
+#[test]
+fn crop_by_time_keeps_window() {
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let at = |secs: f64| start + chrono::TimeDelta::milliseconds((secs * 1000.0) as i64);
+    let points = (0..=100)
+        .map(|i| TrackPoint {
+            lon: i as f64 * 0.0001,
+            time: Some(at(i as f64).to_rfc3339()),
+            ..Default::default()
+        })
+        .collect();
+    let track = Track::new(vec![Segment::new(points)]);
+
+    let cropped = track.crop_by_time(at(10.0), at(90.0)).unwrap();
+    assert_eq!(cropped.total_point_count(), 81);
+
+    let cropped = track.crop_by_time(at(10.5), at(89.5)).unwrap();
+    let pts: Vec<&TrackPoint> = cropped.points().collect();
+    assert_eq!(pts.len(), 81);
+    assert_eq!(pts[0].timestamp(), Some(at(10.5)));
+    assert!((pts[0].lon - 0.00105).abs() < 1e-12);
+    assert_eq!(pts[80].timestamp(), Some(at(89.5)));
+
+    let untimed = Track::new(vec![Segment::new(vec![TrackPoint::default()])]);
+    assert!(matches!(
+        untimed.crop_by_time(at(10.0), at(90.0)),
+        Err(Error::InvalidData { .. })
+    ));
+}
+
+#[test]
+fn nearest_point_across_segments() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"></trkpt>
+              <trkpt lat="0.0" lon="0.001"></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.003"></trkpt>
+              <trkpt lat="0.0" lon="0.004"></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let (s, p, d) = track.nearest_point(0.0, 0.003).unwrap();
+    assert_eq!((s, p), (1, 0));
+    assert!(d < 1e-9);
+
+    // Between the two segments, slightly nearer the first one's end.
+    let (s, p, d) = track.nearest_point(0.0, 0.0019).unwrap();
+    assert_eq!((s, p), (0, 1));
+    assert!((d - 0.0009_f64.to_radians() * crate::gpx::geo::EARTH_RADIUS_M).abs() < 1e-6);
+    assert!(Track::new(Vec::new()).nearest_point(0.0, 0.0).is_none());
+}
+
+#[test]
+fn center_is_bounding_box_midpoint() {
+    let track = Track::new(vec![Segment::new(vec![
+        TrackPoint::builder(10.0, 20.0).build(),
+        TrackPoint::builder(12.0, 20.0).build(),
+        TrackPoint::builder(12.0, 24.0).build(),
+        TrackPoint::builder(10.0, 24.0).build(),
+        // Repeated points do not pull the centre.
+        TrackPoint::builder(10.0, 24.0).build(),
+        TrackPoint::builder(10.0, 24.0).build(),
+    ])]);
+
+    assert_eq!(track.center(), Some((11.0, 22.0)));
+    assert_eq!(Track::new(Vec::new()).center(), None);
+}
+
+#[test]
+fn elevation_profile_ends_at_total_distance() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"><ele>100</ele></trkpt>
+              <trkpt lat="0.0" lon="0.001"></trkpt>
+              <trkpt lat="0.0" lon="0.002"><ele>104</ele></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.005"></trkpt>
+              <trkpt lat="0.0" lon="0.006"><ele>98</ele></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let profile: Vec<(f64, f64)> = track
+        .elevation_profile()
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    assert_eq!(profile.len(), 3);
+    assert_eq!(profile[0], (0.0, 100.0));
+    let (last_distance, last_ele) = profile[2];
+    assert!((last_distance - track.total_distance_m()).abs() < 1e-9);
+    assert_eq!(last_ele, 98.0);
+}
+
+#[test]
+fn bearing_at_delegates_to_segment() {
+    let track = Track::new(vec![
+        Segment::new(vec![TrackPoint::builder(0.0, 0.0).build()]),
+        Segment::new(vec![
+            TrackPoint::builder(0.0, 0.0).build(),
+            TrackPoint::builder(0.0, 0.001).build(),
+        ]),
+    ]);
+
+    assert!((track.bearing_at(1, 0).unwrap() - 90.0).abs() < 1e-6);
+    assert_eq!(track.bearing_at(0, 0), None);
+    assert_eq!(track.bearing_at(2, 0), None);
+}
+
+#[test]
+fn to_polyline_spans_segments() {
+    let track = Track::new(vec![
+        Segment::new(vec![TrackPoint::builder(38.5, -120.2).build()]),
+        Segment::new(vec![
+            TrackPoint::builder(40.7, -120.95).build(),
+            TrackPoint::builder(43.252, -126.453).build(),
+        ]),
+    ]);
+
+    assert_eq!(track.to_polyline(5), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+    assert_eq!(track.to_polyline(6), "_izlhA~rlgdF_{geC~ywl@_kwzCn`{nI");
+
+    let decoded = Track::from_polyline(&track.to_polyline(6), 6).unwrap();
+    assert_eq!(decoded.segment_count(), 1);
+    assert_eq!(decoded.total_point_count(), 3);
+    assert!((decoded.points().last().unwrap().lon + 126.453).abs() < 1e-9);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_totals_match_sequential() {
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let segments = (0..64)
+        .map(|s| {
+            Segment::new(
+                (0..50)
+                    .map(|i| {
+                        let n = s * 50 + i;
+                        TrackPoint::builder((n % 7) as f64 * 1e-4, n as f64 * 1e-4)
+                            .ele((n * 13 % 29) as f64)
+                            .time((start + chrono::TimeDelta::seconds(n)).to_rfc3339())
+                            .build()
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+    let track = Track::new(segments);
+
+    let stats = track.stats_par();
+
+    assert_eq!(
+        track.total_distance_par_m().to_bits(),
+        track.total_distance_m().to_bits()
+    );
+    assert_eq!(
+        stats.distance_m.to_bits(),
+        track.total_distance_m().to_bits()
+    );
+    assert_eq!(
+        (stats.ascent_m, stats.descent_m),
+        track.total_ascent_descent_m()
+    );
+    let serial = track.stats();
+    assert_eq!(stats.point_count, serial.point_count);
+    assert_eq!(stats.duration, serial.duration);
+    assert!((stats.distance_m - serial.distance_m).abs() < 1e-6);
+}
+
+#[test]
+fn segment_endpoints_per_segment() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"></trkpt>
+              <trkpt lat="0.0" lon="0.001"></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.003"></trkpt>
+              <trkpt lat="0.001" lon="0.004"></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let ends = track.segment_endpoints();
+
+    assert_eq!(ends.len(), 2);
+    assert_eq!(ends[0].0.as_ref().unwrap().lon, 0.0);
+    assert_eq!(ends[0].1.as_ref().unwrap().lon, 0.001);
+    assert_eq!(ends[1].0.as_ref().unwrap().lon, 0.003);
+    let last = ends[1].1.as_ref().unwrap();
+    assert_eq!((last.lat, last.lon), (0.001, 0.004));
+    assert_eq!(
+        Track::new(vec![Segment::new(Vec::new())]).segment_endpoints(),
+        [(None, None)]
+    );
+}
+
+#[test]
+fn points_iterates_all_segments() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.0"></trkpt>
+              <trkpt lat="0.0" lon="0.001"></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="0.0" lon="0.002"></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let per_segment: usize = track.segments().iter().map(|s| s.points().len()).sum();
+
+    assert_eq!(track.points().count(), per_segment);
+    assert_eq!((&track).into_iter().count(), per_segment);
+    assert_eq!(track.points().last().unwrap().lon, 0.002);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_json_round_trip() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx>
+            <wpt lat="1.0" lon="2.0"><name>Top</name><sym>Summit</sym></wpt>
+            <trk><trkseg>
+              <trkpt lat="0.0" lon="0.0"><ele>100</ele><time>2024-01-01T00:00:00Z</time></trkpt>
+              <trkpt lat="0.0" lon="0.001"></trkpt>
+            </trkseg></trk>
+        </gpx>"#,
+    ))
+    .unwrap();
+
+    let json = serde_json::to_string(&track).unwrap();
+    let back: Track = serde_json::from_str(&json).unwrap();
+
+    assert!(!json.contains("null"));
+    assert_eq!(back, track);
+}
+
+#[test]
+fn write_csv_rows() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="50.87551" lon="-1.28259"><ele>46.164</ele><time>2024-01-01T00:00:00Z</time></trkpt>
+              <trkpt lat="50.87545" lon="-1.28237"></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="50.87533" lon="-1.28199"></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+
+    let mut out = Vec::new();
+    track.write_csv(&mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let mut lines = text.lines();
+
+    assert_eq!(lines.next(), Some("segment,index,lat,lon,ele,time,hr,cad"));
+    let rows: Vec<Vec<&str>> = lines.map(|l| l.split(',').collect()).collect();
+    assert_eq!(rows.len(), track.total_point_count());
+    assert_eq!(rows[1], ["0", "1", "50.87545", "-1.28237", "", "", "", ""]);
+    for (row, pt) in rows.iter().zip(track.points()) {
+        assert_eq!(row[2].parse::<f64>().unwrap(), pt.lat);
+        assert_eq!(row[3].parse::<f64>().unwrap(), pt.lon);
+    }
+}
+
+#[test]
+fn clone_equals_original() {
+    let track = crate::gpx::parse_track(std::io::Cursor::new(
+        r#"<gpx><trk>
+            <trkseg>
+              <trkpt lat="50.0" lon="-1.0"><ele>10</ele><time>2024-01-01T10:00:00Z</time></trkpt>
+              <trkpt lat="50.0001" lon="-1.0"><time>2024-01-01T10:00:10Z</time></trkpt>
+            </trkseg>
+            <trkseg>
+              <trkpt lat="50.0002" lon="-1.0"><time>2024-01-01T10:00:20Z</time></trkpt>
+              <trkpt lat="50.0003" lon="-1.0"><ele>12</ele></trkpt>
+            </trkseg>
+        </trk></gpx>"#,
+    ))
+    .unwrap();
+    assert_eq!(track.segment_count(), 2);
+    let mut copy = track.clone();
+
+    assert_eq!(copy, track);
+
+    copy.segments.push(track.segments()[0].clone());
+    assert_ne!(copy, track);
+}
+
+#[test]
+fn normalize_cleans_noisy_track() {
+    // One point every 10 s at ~4 km/h along the equator, with a duplicate,
+    // a GPS spike, a sawtooth elevation and a one-hour pause.
+    let mut pts: Vec<TrackPoint> = (0..40)
+        .map(|i| {
+            let secs = i * 10 + if i >= 20 { 3600 } else { 0 };
+            TrackPoint {
+                lat: 0.0,
+                lon: 0.0001 * i as f64,
+                ele: Some(if i % 2 == 0 { 50.0 } else { 60.0 }),
+                time: Some(format!(
+                    "2024-01-01T{:02}:{:02}:{:02}Z",
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60
+                )),
+                ..Default::default()
+            }
+        })
+        .collect();
+    pts.insert(5, pts[4].clone());
+    pts[10].lat = 1.0;
+    let track = Track::new(vec![Segment::new(pts)]);
+
+    let clean = track.normalize(NormalizeOptions {
+        dedup_threshold_m: Some(1.0),
+        max_speed_kmh: Some(50.0),
+        smooth_window: Some(10),
+        split_gap: Some(Duration::from_secs(600)),
+    });
+
+    assert_eq!(clean.segment_count(), 2);
+    // The duplicate goes, as do the spike and the points either side of it.
+    assert_eq!(clean.total_point_count(), 37);
+    assert!(clean.points().all(|p| p.lat == 0.0));
+    assert!(clean.total_ascent_descent_m().0 < track.total_ascent_descent_m().0 / 10.0);
+    assert_eq!(track.normalize(NormalizeOptions::default()), track);
+}
+
+#[test]
+fn checked_ascent_descent_distinguishes_missing_elevation() {
+    let at = |lon: f64, ele: Option<f64>| TrackPoint {
+        lat: 0.0,
+        lon,
+        ele,
+        ..Default::default()
+    };
+
+    let none = Track::new(vec![Segment::new(vec![at(0.0, None), at(0.001, None)])]);
+    assert!(!none.has_elevation_data());
+    assert_eq!(none.total_ascent_descent_m(), (0.0, 0.0));
+    assert_eq!(none.checked_ascent_descent_m(), None);
+
+    let partial = Track::new(vec![
+        Segment::new(vec![at(0.0, None), at(0.001, None)]),
+        Segment::new(vec![
+            at(0.002, Some(10.0)),
+            at(0.003, None),
+            at(0.004, Some(15.0)),
+        ]),
+    ]);
+    assert!(partial.has_elevation_data());
+    assert_eq!(partial.checked_ascent_descent_m(), Some((0.0, 0.0)));
+
+    let flat = Track::new(vec![Segment::new(vec![
+        at(0.0, Some(5.0)),
+        at(0.001, Some(5.0)),
+    ])]);
+    assert_eq!(flat.checked_ascent_descent_m(), Some((0.0, 0.0)));
+}
+
+#[test]
+fn validate_monotonic_time_reports_first_reversal() {
+    let at = |time: Option<&str>| TrackPoint {
+        time: time.map(String::from),
+        ..Default::default()
+    };
+    let track = Track::new(vec![
+        Segment::new(vec![
+            at(Some("2024-01-01T10:00:00Z")),
+            at(Some("2024-01-01T10:00:10Z")),
+        ]),
+        Segment::new(vec![
+            at(None),
+            at(Some("2024-01-01T10:00:10Z")),
+            at(Some("2024-01-01T10:00:05Z")),
+            at(Some("2024-01-01T10:00:01Z")),
+        ]),
+    ]);
+
+    assert_eq!(track.validate_monotonic_time(), Err(4));
+    assert_eq!(
+        Track::new(track.segments()[..1].to_vec()).validate_monotonic_time(),
+        Ok(())
+    );
+}
+
+#[test]
+fn detect_loops_figure_eight() {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    // A lemniscate of Gerono starting and ending at the tip of one lobe,
+    // crossing itself once in the middle.
+    let n = 200;
+    let points = (0..=n)
+        .map(|k| {
+            let t = FRAC_PI_2 + 2.0 * PI * k as f64 / n as f64;
+            TrackPoint::builder(0.01 * t.sin() * t.cos(), 0.01 * t.sin()).build()
+        })
+        .collect();
+    let track = Track::new(vec![Segment::new(points)]);
+
+    assert_eq!(
+        track.detect_loops(15.0),
+        vec![(0, 0, 0, 200), (0, 50, 0, 150)]
+    );
+
+    let straight = Track::new(vec![Segment::new(
+        (0..50)
+            .map(|k| TrackPoint::builder(0.0, k as f64 * 1e-4).build())
+            .collect(),
+    )]);
+    assert!(straight.detect_loops(15.0).is_empty());
+}
+
+#[test]
+fn centroid_of_square_is_its_centre() {
+    let square = Track::new(vec![Segment::new(vec![
+        TrackPoint::builder(10.0, 20.0).build(),
+        TrackPoint::builder(10.0, 20.01).build(),
+        TrackPoint::builder(10.01, 20.01).build(),
+        TrackPoint::builder(10.01, 20.0).build(),
+    ])]);
+
+    let (lat, lon) = square.centroid().unwrap();
+    assert!((lat - 10.005).abs() < 1e-6, "{lat}");
+    assert!((lon - 20.005).abs() < 1e-6, "{lon}");
+
+    let across = Track::new(vec![Segment::new(vec![
+        TrackPoint::builder(0.0, 179.0).build(),
+        TrackPoint::builder(0.0, -179.0).build(),
+    ])]);
+    let (_, lon) = across.centroid().unwrap();
+    assert!((lon.abs() - 180.0).abs() < 1e-9, "{lon}");
+    assert_eq!(Track::new(Vec::new()).centroid(), None);
+}
+
+#[test]
+fn resample_time_ten_second_intervals() {
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let timed = (0..=4)
+        .map(|i| {
+            TrackPoint::builder(0.0, i as f64 * 1e-3)
+                .time((start + chrono::TimeDelta::seconds(15 * i)).to_rfc3339())
+                .build()
+        })
+        .collect();
+    let untimed = vec![
+        TrackPoint::builder(1.0, 0.0).build(),
+        TrackPoint::builder(1.0, 0.001).build(),
+    ];
+    let track = Track::new(vec![Segment::new(timed), Segment::new(untimed)]);
+
+    let resampled = track.resample_time(Duration::from_secs(10));
+
+    let first = &resampled.segments()[0];
+    assert_eq!(first.point_count(), 7);
+    let last = first.last().unwrap();
+    assert_eq!(
+        last.timestamp(),
+        Some(start + chrono::TimeDelta::seconds(60))
+    );
+    assert!((first.points()[3].lon - 0.002).abs() < 1e-9);
+    assert_eq!(resampled.segments()[1], track.segments()[1]);
+}
+
+#[test]
+fn index_by_segment_and_point() {
+    let track = Track::from_path("assert/Portsea_Paused.gpx").unwrap();
+
+    assert_eq!(track.len(), 2);
+    assert!(!track.is_empty());
+    assert_eq!(track[1].len(), 3);
+    let pt = &track[1][0];
+    assert_eq!((pt.lat, pt.lon), (50.81461, -1.08377));
+    assert_eq!(pt.time.as_deref(), Some("2024-05-04T09:14:00Z"));
+    assert!(Track::new(Vec::new()).is_empty());
+}
+
+#[test]
+fn filter_points_drops_emptied_segments() {
+    let track = Track::from_path("assert/Portsea_Paused.gpx").unwrap();
+
+    let high = track.filter_points(|pt| pt.ele > Some(12.0));
+
+    assert_eq!(high.segment_count(), 1);
+    assert_eq!(high[0].len(), 3);
+    assert_eq!(high[0][0].ele, Some(12.7));
+}
+
+#[test]
+fn clip_to_bbox_splits_on_reentry() {
+    let track = Track::new(vec![Segment::new(
+        [0.5, 0.9, 1.0, 1.5, 2.0, 0.8, 0.2, -0.1]
+            .into_iter()
+            .map(|lat| TrackPoint::builder(lat, 0.0).build())
+            .collect(),
+    )]);
+
+    let clipped = track.clip_to_bbox(BoundingBox::new(0.0, -1.0, 1.0, 1.0));
+
+    assert_eq!(clipped.segment_count(), 2);
+    let lats: Vec<Vec<f64>> = clipped
+        .segments()
+        .iter()
+        .map(|s| s.points().iter().map(|p| p.lat).collect())
+        .collect();
+    assert_eq!(lats, [vec![0.5, 0.9, 1.0], vec![0.8, 0.2]]);
+    assert_eq!(
+        track.bounding_box(),
+        Some(BoundingBox::new(-0.1, 0.0, 2.0, 0.0))
+    );
+}
+
+#[test]
+fn split_by_time_gap_separates_activities() {
+    let start = DateTime::parse_from_rfc3339("2024-01-01T07:00:00Z").unwrap();
+    let seg = |minutes: &[i64]| {
+        Segment::new(
+            minutes
+                .iter()
+                .map(|&m| {
+                    TrackPoint::builder(0.0, m as f64 * 1e-4)
+                        .time((start + chrono::TimeDelta::minutes(m)).to_rfc3339())
+                        .build()
+                })
+                .collect(),
+        )
+    };
+    // A run with a short pause between laps, then an evening walk three
+    // hours later whose first segment continues the run's last segment.
+    let track = Track::new(vec![
+        seg(&[0, 5, 10]),
+        seg(&[12, 20, 25, 205, 210]),
+        seg(&[215, 220]),
+    ]);
+
+    let parts = track.split_by_time_gap(Duration::from_secs(3600));
+
+    assert_eq!(parts.len(), 2);
+    let shape = |t: &Track| t.segments().iter().map(Segment::len).collect::<Vec<_>>();
+    assert_eq!(shape(&parts[0]), [3, 3]);
+    assert_eq!(shape(&parts[1]), [2, 2]);
+
+    let across = Track::new(vec![seg(&[0, 5]), seg(&[200, 205])]);
+    assert_eq!(across.split_by_time_gap(Duration::from_secs(3600)).len(), 2);
+    assert!(
+        Track::new(Vec::new())
+            .split_by_time_gap(Duration::from_secs(60))
+            .is_empty()
+    );
+}
+
+#[test]
+fn avg_pace_spans_segments() {
+    let track = Track::from_path("assert/Portsea_Paused.gpx").unwrap();
+    let minutes = 16.0;
+
+    let pace = track.avg_pace_min_per_km().unwrap();
+
+    assert!((pace - minutes / (track.total_distance_m() / 1000.0)).abs() < 1e-9);
+    assert_eq!(track.pace_profile().len(), 5);
+    assert_eq!(Track::new(Vec::new()).avg_pace_min_per_km(), None);
+}
+
+#[test]
+fn elevation_range_and_net_change() {
+    let at = |ele: Option<f64>| TrackPoint {
+        ele,
+        ..Default::default()
+    };
+    let track = Track::new(vec![
+        Segment::new(vec![at(None), at(Some(120.0)), at(Some(180.0))]),
+        Segment::new(vec![
+            at(Some(310.0)),
+            at(Some(240.0)),
+            at(Some(95.0)),
+            at(None),
+        ]),
+    ]);
+
+    assert_eq!(track.elevation_range(), Some((95.0, 310.0)));
+    assert_eq!(track.net_elevation_change(), Some(-25.0));
+
+    let flat = Track::new(vec![Segment::new(vec![at(None), at(None)])]);
+    assert_eq!(flat.elevation_range(), None);
+    assert_eq!(flat.net_elevation_change(), None);
+}
+
+#[test]
+fn nearest_point_finds_fixture_vertex() {
+    let track = Track::from_path("assert/Portsea_Paused.gpx").unwrap();
+
+    let (seg, pt, d) = track.nearest_point(50.81503, -1.08302).unwrap();
+    assert_eq!((seg, pt), (1, 1));
+    assert!(d < 2.0, "{d}");
+
+    // Halfway along the first leg of the second segment.
+    let (seg, pt, d, snapped) = track.nearest_point_on_line(50.814815, -1.08339).unwrap();
+    assert_eq!((seg, pt), (1, 0));
+    assert!(d < 0.1, "{d}");
+    assert!((snapped.ele.unwrap() - 13.9).abs() < 1e-3);
+    assert!(
+        Track::new(Vec::new())
+            .nearest_point_on_line(0.0, 0.0)
+            .is_none()
+    );
+}
+
+#[test]
+fn hr_zone_time_alternating_zones() {
+    let start = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap();
+    let points = (0..=100)
+        .map(|i| {
+            TrackPoint::builder(0.0, i as f64 * 1e-5)
+                .time((start + chrono::TimeDelta::seconds(i)).to_rfc3339())
+                .hr(if i % 2 == 0 { 130 } else { 170 })
+                .build()
+        })
+        .collect();
+    let track = Track::new(vec![Segment::new(points)]);
+
+    let zones = track.hr_zone_time(&[(120, 149), (150, 179), (180, 220)]);
+
+    assert_eq!(
+        zones,
+        [
+            Duration::from_secs(50),
+            Duration::from_secs(50),
+            Duration::ZERO
+        ]
+    );
+    let no_hr = Track::new(vec![Segment::new(vec![TrackPoint::default(); 3])]);
+    assert!(no_hr.hr_zone_time(&[(120, 149)]).is_empty());
 }
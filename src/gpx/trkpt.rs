@@ -1,31 +1,196 @@
-use crate::gpx::{Error, Segment, Track, err::InternalError};
+use crate::gpx::{
+    ElevationSource, Error, ParseOptions, Segment, Track, Waypoint,
+    err::InternalError,
+    metadata::{Metadata, MetadataBuilder},
+    segment::haversine_m,
+    wpt::{self, WptApplyfn},
+};
 use std::io::BufRead;
 
+use chrono::{DateTime, FixedOffset};
 use quick_xml::{
     Reader,
-    events::{BytesStart, BytesText, Event},
+    events::{BytesCData, BytesStart, BytesText, Event},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TrackPoint {
     pub lat: f64,
     pub lon: f64,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub time: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub ele: Option<f64>,
+    /// Heart rate in beats per minute, from a `TrackPointExtension`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub hr: Option<u8>,
+    /// Cadence in revolutions per minute, from a `TrackPointExtension`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub cadence: Option<u16>,
+    /// Power in watts, from a `<power>`, `<gpxtpx:watts>` or `PowerInWatts`
+    /// extension.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub power: Option<u16>,
+    /// Horizontal dilution of precision of the fix.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub hdop: Option<f64>,
+    /// Vertical dilution of precision of the fix.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub vdop: Option<f64>,
+    /// Position dilution of precision of the fix.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub pdop: Option<f64>,
+    /// Number of satellites used for the fix.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub sat: Option<u16>,
+    /// Type of fix: `none`, `2d`, `3d`, `dgps` or `pps`.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub fix: Option<String>,
+    /// Height of the geoid above the WGS-84 ellipsoid, in metres.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub geoidheight: Option<f64>,
+}
+
+impl TrackPoint {
+    /// Parses `time` as an RFC 3339 timestamp. Returns `None` when the point
+    /// has no time or it is not valid RFC 3339.
+    pub fn timestamp(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(self.time.as_deref()?).ok()
+    }
+
+    /// Returns the speed in km/h from this point to `other`: the haversine
+    /// distance over the time between them. `None` if either has no
+    /// timestamp or both have the same one.
+    pub fn speed_to(&self, other: &TrackPoint) -> Option<f64> {
+        let ms = (other.timestamp()? - self.timestamp()?).num_milliseconds();
+        (ms != 0).then(|| haversine_m(self, other) / (ms as f64 / 1000.0) * 3.6)
+    }
+
+    /// Starts building a point at `(lat, lon)` with every optional field
+    /// unset.
+    pub fn builder(lat: f64, lon: f64) -> TrackPointBuilder {
+        TrackPointBuilder {
+            point: TrackPoint {
+                lat,
+                lon,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Formats as e.g. `(lat=48.1234, lon=11.5678, ele=530.2m,
+/// time=2024-06-01T10:00:00Z)`, omitting absent fields. Sensor extensions
+/// are shown as `hr=..bpm`, `cad=..rpm` and `power=..W`.
+impl std::fmt::Display for TrackPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(lat={}, lon={}", self.lat, self.lon)?;
+        if let Some(ele) = self.ele {
+            write!(f, ", ele={ele}m")?;
+        }
+        if let Some(time) = &self.time {
+            write!(f, ", time={time}")?;
+        }
+        if let Some(hr) = self.hr {
+            write!(f, ", hr={hr}bpm")?;
+        }
+        if let Some(cadence) = self.cadence {
+            write!(f, ", cad={cadence}rpm")?;
+        }
+        if let Some(power) = self.power {
+            write!(f, ", power={power}W")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Chained constructor for [`TrackPoint`], see [`TrackPoint::builder`].
+#[derive(Debug, Clone)]
+pub struct TrackPointBuilder {
+    point: TrackPoint,
+}
+
+impl TrackPointBuilder {
+    pub fn ele(mut self, ele: f64) -> Self {
+        self.point.ele = Some(ele);
+        self
+    }
+
+    pub fn time(mut self, time: impl Into<String>) -> Self {
+        self.point.time = Some(time.into());
+        self
+    }
+
+    pub fn hr(mut self, hr: u8) -> Self {
+        self.point.hr = Some(hr);
+        self
+    }
+
+    pub fn cadence(mut self, cadence: u16) -> Self {
+        self.point.cadence = Some(cadence);
+        self
+    }
+
+    pub fn power(mut self, power: u16) -> Self {
+        self.point.power = Some(power);
+        self
+    }
+
+    pub fn hdop(mut self, hdop: f64) -> Self {
+        self.point.hdop = Some(hdop);
+        self
+    }
+
+    pub fn vdop(mut self, vdop: f64) -> Self {
+        self.point.vdop = Some(vdop);
+        self
+    }
+
+    pub fn pdop(mut self, pdop: f64) -> Self {
+        self.point.pdop = Some(pdop);
+        self
+    }
+
+    pub fn sat(mut self, sat: u16) -> Self {
+        self.point.sat = Some(sat);
+        self
+    }
+
+    pub fn fix(mut self, fix: impl Into<String>) -> Self {
+        self.point.fix = Some(fix.into());
+        self
+    }
+
+    pub fn geoidheight(mut self, geoidheight: f64) -> Self {
+        self.point.geoidheight = Some(geoidheight);
+        self
+    }
+
+    pub fn build(self) -> TrackPoint {
+        self.point
+    }
 }
 
 type Applyfn = fn(&mut TrackPoint, &str) -> Result<(), InternalError>;
 
-struct TextHandler {
-    tag: &'static [u8],
-    apply: Applyfn,
+pub(crate) struct TextHandler<T> {
+    pub(crate) tag: &'static [u8],
+    pub(crate) apply: fn(&mut T, &str) -> Result<(), InternalError>,
+}
+
+pub(crate) fn parse_number<T: std::str::FromStr>(s: &str, name: &str) -> Result<T, InternalError> {
+    s.parse::<T>()
+        .map_err(|_| InternalError::InvalidTrackPoint(format!("{name} is not a number")))
 }
 
 fn apply_ele(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
-    let v = s
-        .parse::<f64>()
-        .map_err(|_| InternalError::InvalidTrackPoint("ele is not a number".into()))?;
-    pt.ele = Some(v);
+    pt.ele = Some(parse_number(s, "ele")?);
+    Ok(())
+}
+
+fn apply_hr(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.hr = Some(parse_number(s, "hr")?);
     Ok(())
 }
 
@@ -34,7 +199,47 @@ fn apply_time(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
     Ok(())
 }
 
-const HANDLERS: &[TextHandler] = &[
+fn apply_cadence(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.cadence = Some(parse_number(s, "cad")?);
+    Ok(())
+}
+
+fn apply_power(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.power = Some(parse_number(s, "power")?);
+    Ok(())
+}
+
+fn apply_hdop(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.hdop = Some(parse_number(s, "hdop")?);
+    Ok(())
+}
+
+fn apply_vdop(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.vdop = Some(parse_number(s, "vdop")?);
+    Ok(())
+}
+
+fn apply_pdop(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.pdop = Some(parse_number(s, "pdop")?);
+    Ok(())
+}
+
+fn apply_sat(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.sat = Some(parse_number(s, "sat")?);
+    Ok(())
+}
+
+fn apply_fix(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.fix = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_geoidheight(pt: &mut TrackPoint, s: &str) -> Result<(), InternalError> {
+    pt.geoidheight = Some(parse_number(s, "geoidheight")?);
+    Ok(())
+}
+
+const HANDLERS: &[TextHandler<TrackPoint>] = &[
     TextHandler {
         tag: b"time",
         apply: apply_time,
@@ -43,138 +248,623 @@ const HANDLERS: &[TextHandler] = &[
         tag: b"ele",
         apply: apply_ele,
     },
+    TextHandler {
+        tag: b"hr",
+        apply: apply_hr,
+    },
+    TextHandler {
+        tag: b"cad",
+        apply: apply_cadence,
+    },
+    TextHandler {
+        tag: b"power",
+        apply: apply_power,
+    },
+    TextHandler {
+        tag: b"PowerInWatts",
+        apply: apply_power,
+    },
+    TextHandler {
+        tag: b"watts",
+        apply: apply_power,
+    },
+    TextHandler {
+        tag: b"hdop",
+        apply: apply_hdop,
+    },
+    TextHandler {
+        tag: b"vdop",
+        apply: apply_vdop,
+    },
+    TextHandler {
+        tag: b"pdop",
+        apply: apply_pdop,
+    },
+    TextHandler {
+        tag: b"sat",
+        apply: apply_sat,
+    },
+    TextHandler {
+        tag: b"fix",
+        apply: apply_fix,
+    },
+    TextHandler {
+        tag: b"geoidheight",
+        apply: apply_geoidheight,
+    },
 ];
 
+/// Where the text of the current child element of a `<trkpt>` goes.
+#[derive(Clone, Copy)]
+enum Target {
+    Point(Applyfn),
+    ExtensionEle,
+}
+
+/// Accumulates the children of the `<trkpt>` currently being parsed.
+#[derive(Default)]
+struct PointBuilder {
+    point: Option<TrackPoint>,
+    target: Option<Target>,
+    in_extensions: bool,
+    extension_ele: Option<f64>,
+}
+
+impl PointBuilder {
+    fn is_active(&self) -> bool {
+        self.point.is_some()
+    }
+
+    fn begin(&mut self, e: &BytesStart) -> Result<(), InternalError> {
+        *self = PointBuilder {
+            point: Some(parse_trkpt(e)?),
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    fn child_start(&mut self, local_name: &[u8]) {
+        self.target = match local_name {
+            b"extensions" => {
+                self.in_extensions = true;
+                None
+            }
+            b"ele" | b"altitude" if self.in_extensions => Some(Target::ExtensionEle),
+            tag => find_handler(tag).map(Target::Point),
+        };
+    }
+
+    fn child_end(&mut self, local_name: &[u8]) {
+        if local_name == b"extensions" {
+            self.in_extensions = false;
+        }
+        self.target = None;
+    }
+
+    fn text(&mut self, s: &str) -> Result<(), InternalError> {
+        match (self.point.as_mut(), self.target) {
+            (Some(pt), Some(Target::Point(apply))) => apply(pt, s),
+            (Some(_), Some(Target::ExtensionEle)) => {
+                self.extension_ele = Some(parse_number(s, "extension ele")?);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Completes the point, picking `ele` from the first source in
+    /// `priority` that supplied a value.
+    fn finish(&mut self, priority: &[ElevationSource]) -> Option<TrackPoint> {
+        let mut pt = self.point.take()?;
+        let gpx_ele = pt.ele;
+        pt.ele = priority.iter().find_map(|source| match source {
+            ElevationSource::Gpx => gpx_ele,
+            ElevationSource::Extension => self.extension_ele,
+        });
+        self.target = None;
+        Some(pt)
+    }
+}
+
+/// Parses a GPX 1.0 or 1.1 document. Both versions share the `<trkpt>`,
+/// `<ele>` and `<time>` structure; 1.0-only children such as `<course>` and
+/// `<speed>` are ignored.
 pub fn parse_track<R: BufRead>(reader: R) -> Result<Track, Error> {
+    parse_track_with_options(reader, ParseOptions::default())
+}
+
+/// Parses a GPX document held in memory. See also `str::parse::<Track>()`.
+pub fn parse_track_str(gpx: &str) -> Result<Track, Error> {
+    parse_track_bytes(gpx.as_bytes())
+}
+
+/// Parses a GPX document held in memory, e.g. a request body.
+pub fn parse_track_bytes(gpx: &[u8]) -> Result<Track, Error> {
+    parse_track(gpx)
+}
+
+/// Like [`parse_track`], but fails with [`Error::NoTrackData`] instead of
+/// returning an empty track when the document has no track points, e.g. a
+/// waypoints-only file or some other kind of XML.
+pub fn parse_track_strict<R: BufRead>(reader: R) -> Result<Track, Error> {
+    let track = parse_track(reader)?;
+    if track.total_point_count() == 0 {
+        return Err(Error::NoTrackData);
+    }
+    Ok(track)
+}
+
+/// Parses a gzip-compressed GPX stream (e.g. a `.gpx.gz` file).
+#[cfg(feature = "flate2")]
+pub fn parse_track_gz<R: BufRead>(reader: R) -> Result<Track, Error> {
+    let decoder = flate2::bufread::GzDecoder::new(reader);
+    parse_track(std::io::BufReader::new(decoder))
+}
+
+pub fn parse_track_with_options<R: BufRead>(reader: R, opts: ParseOptions) -> Result<Track, Error> {
+    let mut parser = TrackParser::new(opts);
+    feed(reader, &mut parser)?;
+    Ok(parser.finish())
+}
+
+/// Like [`parse_track`], but skips invalid `<trkpt>`s (see
+/// [`ParseOptions::skip_invalid_points`]) and returns their errors alongside
+/// the track instead of failing. A malformed document still stops parsing;
+/// its error is the last one returned and the track holds the segments
+/// completed before it.
+pub fn parse_track_lenient<R: BufRead>(reader: R) -> (Track, Vec<Error>) {
+    let mut parser = TrackParser::new(ParseOptions {
+        skip_invalid_points: true,
+        ..Default::default()
+    });
+    let fatal = feed(reader, &mut parser).err();
+
+    let mut errors: Vec<Error> = std::mem::take(&mut parser.skipped)
+        .into_iter()
+        .map(Error::from)
+        .collect();
+    errors.extend(fatal.map(Error::from));
+    (parser.finish(), errors)
+}
+
+/// Reads `reader` to the end, handing every event to `parser`.
+fn feed<R: BufRead>(mut reader: R, parser: &mut TrackParser) -> Result<(), InternalError> {
+    skip_bom(&mut reader)?;
     let mut xml = Reader::from_reader(reader);
     xml.trim_text(true);
 
     let mut buf = Vec::new();
-    let mut segments: Vec<Segment> = Vec::new();
-    let mut current_points: Vec<TrackPoint> = Vec::new();
-    let mut current_handler: Option<Applyfn> = None;
-    let mut current_point: Option<TrackPoint> = None;
-
     loop {
-        match xml.read_event_into(&mut buf).map_err(InternalError::from)? {
-            Event::Start(e) if e.name().as_ref() == b"trkseg" => {
-                current_points.clear();
+        match xml
+            .read_event_into(&mut buf)
+            .map_err(|e| InternalError::from(e).at(xml.buffer_position()))?
+        {
+            Event::Eof => return Ok(()),
+            event => parser.handle(event)?,
+        }
+        buf.clear();
+    }
+}
+
+/// Event-driven state behind [`parse_track_with_options`], kept separate from
+/// the read loop so the sync and async front ends build tracks the same way.
+pub(crate) struct TrackParser {
+    opts: ParseOptions,
+    segments: Vec<Segment>,
+    current_points: Vec<TrackPoint>,
+    current_point: PointBuilder,
+    waypoints: Vec<Waypoint>,
+    current_wpt_handler: Option<WptApplyfn>,
+    current_wpt: Option<Waypoint>,
+    point_count: usize,
+    /// Errors of the points dropped under `skip_invalid_points`.
+    skipped: Vec<InternalError>,
+    current_metadata: Option<MetadataBuilder>,
+    metadata: Option<Metadata>,
+    creator: Option<String>,
+    version: Option<String>,
+    /// Element depth below the `<trk>` being parsed, if any.
+    trk_depth: Option<usize>,
+    in_trk_extensions: bool,
+    in_display_color: bool,
+    display_color: Option<String>,
+}
+
+impl TrackParser {
+    pub(crate) fn new(opts: ParseOptions) -> Self {
+        TrackParser {
+            opts,
+            segments: Vec::new(),
+            current_points: Vec::new(),
+            current_point: PointBuilder::default(),
+            waypoints: Vec::new(),
+            current_wpt_handler: None,
+            current_wpt: None,
+            point_count: 0,
+            skipped: Vec::new(),
+            current_metadata: None,
+            metadata: None,
+            creator: None,
+            version: None,
+            trk_depth: None,
+            in_trk_extensions: false,
+            in_display_color: false,
+            display_color: None,
+        }
+    }
+
+    /// Drops the `<trkpt>` being parsed if the options allow skipping
+    /// `error`, otherwise returns it.
+    fn skip_point(&mut self, error: InternalError) -> Result<(), InternalError> {
+        match error {
+            InternalError::InvalidTrackPoint(_) if self.opts.skip_invalid_points => {
+                self.current_point = PointBuilder::default();
+                self.skipped.push(error);
+                Ok(())
+            }
+            error => Err(error),
+        }
+    }
+
+    pub(crate) fn handle(&mut self, event: Event) -> Result<(), InternalError> {
+        if let Some(depth) = self.trk_depth.as_mut() {
+            match &event {
+                Event::Start(_) => *depth += 1,
+                Event::End(_) => *depth = depth.saturating_sub(1),
+                _ => {}
             }
+        }
 
-            Event::End(e) if e.name().as_ref() == b"trkseg" => {
-                if !current_points.is_empty() {
-                    segments.push(Segment::new(std::mem::take(&mut current_points)));
+        match event {
+            Event::Start(e) if e.local_name().as_ref() == b"gpx" => {
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    match attr.key.as_ref() {
+                        b"creator" => self.creator = Some(attr.unescape_value()?.into_owned()),
+                        b"version" => self.version = Some(attr.unescape_value()?.into_owned()),
+                        _ => {}
+                    }
                 }
             }
 
-            Event::Start(e) if e.name().as_ref() == b"trkpt" => {
-                current_point = Some(parse_trkpt(&e)?);
-                current_handler = None;
+            Event::Start(e) if e.local_name().as_ref() == b"metadata" => {
+                self.current_metadata = Some(MetadataBuilder::default());
             }
 
-            Event::End(e) if e.name().as_ref() == b"trkpt" => {
-                if let Some(pt) = current_point.take() {
-                    current_points.push(pt);
+            Event::End(e) if e.local_name().as_ref() == b"metadata" => {
+                if let Some(meta) = self.current_metadata.take() {
+                    self.metadata = Some(meta.finish());
                 }
-                current_handler = None;
             }
 
-            Event::Start(e) => {
-                if current_point.is_some() {
-                    current_handler = find_handler(e.name().as_ref());
+            Event::Start(e) if self.current_metadata.is_some() => {
+                if let Some(meta) = self.current_metadata.as_mut() {
+                    meta.start(&e)?;
                 }
             }
 
-            Event::Text(e) => {
-                if let (Some(ref mut pt), Some(apply)) = (current_point.as_mut(), current_handler) {
-                    let s = read_text_string(e)?;
-                    apply(pt, &s)?;
+            Event::Empty(e) if self.current_metadata.is_some() => {
+                if let Some(meta) = self.current_metadata.as_mut() {
+                    meta.empty(&e)?;
                 }
             }
 
-            Event::End(_) => {
-                current_handler = None;
+            Event::Start(e) if e.local_name().as_ref() == b"trk" => {
+                self.trk_depth = Some(0);
             }
 
-            Event::Eof => break,
-            _ => {}
-        }
+            Event::End(e) if e.local_name().as_ref() == b"trk" => {
+                self.trk_depth = None;
+                self.in_trk_extensions = false;
+            }
 
-        buf.clear();
-    }
+            // Only `<trk>`'s own `<extensions>`, not those of its points.
+            Event::Start(e)
+                if self.trk_depth == Some(1) && e.local_name().as_ref() == b"extensions" =>
+            {
+                self.in_trk_extensions = true;
+            }
 
-    Ok(Track::new(segments))
-}
+            Event::End(e)
+                if self.trk_depth == Some(0) && e.local_name().as_ref() == b"extensions" =>
+            {
+                self.in_trk_extensions = false;
+            }
 
-pub fn parse_track_points<R: BufRead>(reader: R) -> Result<Vec<TrackPoint>, Error> {
-    let mut xml = Reader::from_reader(reader);
-    xml.trim_text(true);
+            Event::Start(e)
+                if self.in_trk_extensions && e.local_name().as_ref() == b"DisplayColor" =>
+            {
+                self.in_display_color = true;
+            }
 
-    let mut buf = Vec::new();
-    let mut points = Vec::new();
-    let mut current: Option<TrackPoint> = None;
-    let mut current_handler: Option<Applyfn> = None;
+            Event::Start(e) if e.local_name().as_ref() == b"trkseg" => {
+                self.current_points.clear();
+            }
 
-    loop {
-        match xml.read_event_into(&mut buf).map_err(InternalError::from)? {
-            Event::Start(e) if e.name().as_ref() == b"trkpt" => {
-                current = Some(parse_trkpt(&e)?);
-                current_handler = None;
+            Event::End(e)
+                if e.local_name().as_ref() == b"trkseg" && !self.current_points.is_empty() =>
+            {
+                let points = std::mem::take(&mut self.current_points);
+                if points.len() >= self.opts.min_segment_points {
+                    self.segments.push(Segment::new(points));
+                } else if let Some(prev) = self.segments.pop() {
+                    let mut merged = prev.points().to_vec();
+                    merged.extend(points);
+                    self.segments.push(Segment::new(merged));
+                }
             }
 
-            Event::Start(e) => {
-                current_handler = if current.is_some() {
-                    find_handler(e.name().as_ref())
-                } else {
-                    None
-                };
+            Event::Start(e) if e.local_name().as_ref() == b"trkpt" => {
+                if let Err(error) = self.current_point.begin(&e) {
+                    self.skip_point(error)?;
+                }
             }
 
-            Event::Text(e) => {
-                if let (Some(ref mut pt), Some(apply)) = (current.as_mut(), current_handler) {
-                    let s = read_text_string(e)?;
-                    apply(pt, &s)?;
+            Event::End(e) if e.local_name().as_ref() == b"trkpt" => {
+                let Some(pt) = self.current_point.finish(&self.opts.elevation_priority) else {
+                    return Ok(());
+                };
+                if self.opts.strict_elevation && pt.ele.is_none() {
+                    return self.skip_point(InternalError::InvalidTrackPoint(
+                        "trkpt has no elevation.".into(),
+                    ));
+                }
+                if self
+                    .opts
+                    .max_points
+                    .is_some_and(|max| self.point_count >= max)
+                {
+                    return Err(InternalError::InvalidTrackPoint(format!(
+                        "more than {} track points.",
+                        self.point_count
+                    )));
                 }
+                self.point_count += 1;
+                self.current_points.push(pt);
+            }
+
+            Event::Start(e) if e.local_name().as_ref() == b"wpt" => {
+                self.current_wpt = Some(begin_waypoint(&e)?);
+                self.current_wpt_handler = None;
+            }
+
+            Event::Empty(e) if e.local_name().as_ref() == b"wpt" => {
+                self.waypoints.push(begin_waypoint(&e)?);
             }
 
-            Event::End(e) if e.name().as_ref() == b"trkpt" => {
-                if let Some(pt) = current.take() {
-                    points.push(pt);
+            Event::End(e) if e.local_name().as_ref() == b"wpt" => {
+                if let Some(wpt) = self.current_wpt.take() {
+                    self.waypoints.push(wpt);
                 }
+                self.current_wpt_handler = None;
+            }
+
+            Event::Start(e) if self.current_point.is_active() => {
+                self.current_point.child_start(e.local_name().as_ref());
             }
 
-            Event::End(_) => {
-                current_handler = None;
+            Event::Start(e) if self.current_wpt.is_some() => {
+                self.current_wpt_handler = wpt::find_handler(e.local_name().as_ref());
+            }
+
+            Event::Text(e) => self.text(|| read_text_string(e))?,
+
+            Event::CData(e) => self.text(|| read_cdata_string(e))?,
+
+            Event::End(e) => {
+                self.current_point.child_end(e.local_name().as_ref());
+                self.current_wpt_handler = None;
+                self.in_display_color = false;
+                if let Some(meta) = self.current_metadata.as_mut() {
+                    meta.end();
+                }
             }
 
-            Event::Eof => break,
             _ => {}
         }
-        buf.clear();
+        Ok(())
+    }
+
+    /// Hands the content of a text or CDATA node to the point or waypoint
+    /// child being parsed. `read` is only called when someone wants it.
+    fn text(
+        &mut self,
+        read: impl FnOnce() -> Result<String, InternalError>,
+    ) -> Result<(), InternalError> {
+        if self.current_point.is_active() {
+            if let Err(error) = self.current_point.text(&read()?) {
+                self.skip_point(error)?;
+            }
+        } else if let (Some(wpt), Some(apply)) =
+            (self.current_wpt.as_mut(), self.current_wpt_handler)
+        {
+            apply(wpt, &read()?)?;
+        } else if let Some(meta) = self.current_metadata.as_mut()
+            && meta.wants_text()
+        {
+            meta.text(&read()?)?;
+        } else if self.in_display_color && self.display_color.is_none() {
+            self.display_color = Some(read()?);
+        }
+        Ok(())
     }
-    Ok(points)
+
+    pub(crate) fn finish(self) -> Track {
+        let mut track = Track::new(self.segments);
+        track.waypoints = self.waypoints;
+        track.metadata = self.metadata;
+        track.creator = self.creator;
+        track.version = self.version;
+        track.display_color = self.display_color;
+        track
+    }
+}
+
+pub fn parse_track_points<R: BufRead>(reader: R) -> Result<Vec<TrackPoint>, Error> {
+    TrackPointStream::new(reader).collect()
+}
+
+/// Lazily yields the `<trkpt>`s of a GPX stream one at a time, so memory use
+/// stays flat regardless of file size. Waypoints are ignored; segment
+/// boundaries are exposed through [`TrackPointStream::current_segment_index`].
+/// The stream ends after the first error.
+pub struct TrackPointStream<R: BufRead> {
+    xml: Reader<R>,
+    buf: Vec<u8>,
+    current: PointBuilder,
+    segment: Option<usize>,
+    priority: Vec<ElevationSource>,
+    bom_checked: bool,
+    done: bool,
+}
+
+impl<R: BufRead> TrackPointStream<R> {
+    pub fn new(reader: R) -> Self {
+        let mut xml = Reader::from_reader(reader);
+        xml.trim_text(true);
+        TrackPointStream {
+            xml,
+            buf: Vec::new(),
+            current: PointBuilder::default(),
+            segment: None,
+            priority: ParseOptions::default().elevation_priority,
+            bom_checked: false,
+            done: false,
+        }
+    }
+
+    /// Index of the `<trkseg>` the most recently yielded point belongs to,
+    /// or `None` before the first one. Every `<trkseg>` in the document is
+    /// counted, including empty ones, so this can run ahead of
+    /// [`Track::segments`] for files with empty segments.
+    pub fn current_segment_index(&self) -> Option<usize> {
+        self.segment
+    }
+
+    fn next_point(&mut self) -> Result<Option<TrackPoint>, InternalError> {
+        if !self.bom_checked {
+            skip_bom(self.xml.get_mut())?;
+            self.bom_checked = true;
+        }
+        loop {
+            self.buf.clear();
+            match self
+                .xml
+                .read_event_into(&mut self.buf)
+                .map_err(|e| InternalError::from(e).at(self.xml.buffer_position()))?
+            {
+                Event::Start(e) if e.local_name().as_ref() == b"trkseg" => {
+                    self.segment = Some(self.segment.map_or(0, |i| i + 1));
+                }
+
+                Event::Start(e) if e.local_name().as_ref() == b"trkpt" => {
+                    self.current.begin(&e)?;
+                }
+
+                Event::Start(e) if self.current.is_active() => {
+                    self.current.child_start(e.local_name().as_ref());
+                }
+
+                Event::Text(e) if self.current.is_active() => {
+                    self.current.text(&read_text_string(e)?)?;
+                }
+
+                Event::CData(e) if self.current.is_active() => {
+                    self.current.text(&read_cdata_string(e)?)?;
+                }
+
+                Event::End(e) if e.local_name().as_ref() == b"trkpt" => {
+                    if let Some(pt) = self.current.finish(&self.priority) {
+                        return Ok(Some(pt));
+                    }
+                }
+
+                Event::End(e) => {
+                    self.current.child_end(e.local_name().as_ref());
+                }
+
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TrackPointStream<R> {
+    type Item = Result<TrackPoint, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_point() {
+            Ok(Some(pt)) => Some(Ok(pt)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
+pub(crate) const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Consumes a leading UTF-8 byte-order mark, as written by some Windows
+/// tools, so the XML reader starts at the declaration.
+pub(crate) fn skip_bom<R: BufRead>(reader: &mut R) -> Result<(), InternalError> {
+    if reader.fill_buf()?.starts_with(UTF8_BOM) {
+        reader.consume(UTF8_BOM.len());
+    }
+    Ok(())
 }
 
 fn find_handler(tag: &[u8]) -> Option<Applyfn> {
     HANDLERS.iter().find(|h| h.tag == tag).map(|h| h.apply)
 }
 
-fn read_text_string(e: BytesText) -> Result<String, InternalError> {
+pub(crate) fn read_text_string(e: BytesText) -> Result<String, InternalError> {
     Ok(e.unescape().map_err(InternalError::from)?.to_string())
 }
 
+/// Reads a `<![CDATA[...]]>` section, trimmed like text nodes are.
+pub(crate) fn read_cdata_string(e: BytesCData) -> Result<String, InternalError> {
+    let s = std::str::from_utf8(&e).map_err(|e| quick_xml::Error::NonDecodable(Some(e)))?;
+    Ok(s.trim().to_string())
+}
+
 fn parse_attr_f64(
     attr: &quick_xml::events::attributes::Attribute,
     name: &'static str,
 ) -> Result<f64, InternalError> {
-    let value = std::str::from_utf8(&attr.value)
-        .map_err(|_| InternalError::InvalidTrackPoint("lat is not valid utf8.".into()))?;
-    value
-        .parse::<f64>()
-        .map_err(|_| InternalError::InvalidTrackPoint(format!("{name} is not a number")))
+    let value = std::str::from_utf8(&attr.value).map_err(|_| {
+        let snippet: Vec<String> = attr
+            .value
+            .iter()
+            .take(16)
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        InternalError::InvalidTrackPoint(format!(
+            "{name} is not valid utf8 (bytes: {}).",
+            snippet.join(" ")
+        ))
+    })?;
+    parse_number(value, name)
 }
 
 pub fn parse_trkpt(e: &BytesStart) -> Result<TrackPoint, InternalError> {
+    let (lat, lon) = parse_lat_lon(e, "trkpt")?;
+    Ok(TrackPoint {
+        lat,
+        lon,
+        ..Default::default()
+    })
+}
+
+fn parse_lat_lon(e: &BytesStart, tag: &str) -> Result<(f64, f64), InternalError> {
     let mut lat = None;
     let mut lon = None;
     for attr in e.attributes() {
@@ -185,17 +875,41 @@ pub fn parse_trkpt(e: &BytesStart) -> Result<TrackPoint, InternalError> {
             _ => {}
         }
     }
+    check_lat_lon(lat, lon, tag)
+}
+
+/// Starts a [`Waypoint`] with the position of a `<wpt>` tag.
+fn begin_waypoint(e: &BytesStart) -> Result<Waypoint, InternalError> {
+    let (lat, lon) = parse_lat_lon(e, "wpt")?;
+    Ok(Waypoint {
+        lat,
+        lon,
+        ele: None,
+        time: None,
+        name: None,
+        desc: None,
+        sym: None,
+        classification: None,
+    })
+}
 
+/// Validates coordinates read from a `tag` element, requiring both.
+pub(crate) fn check_lat_lon(
+    lat: Option<f64>,
+    lon: Option<f64>,
+    tag: &str,
+) -> Result<(f64, f64), InternalError> {
     match (lat, lon) {
-        (Some(lat), Some(lon)) => Ok(TrackPoint {
-            lat,
-            lon,
-            time: None,
-            ele: None,
-        }),
-        _ => Err(InternalError::InvalidTrackPoint(
-            "trkpt missing lat or lon.".into(),
+        (Some(lat), _) if !(-90.0..=90.0).contains(&lat) => Err(InternalError::InvalidTrackPoint(
+            format!("{tag} lat {lat} is out of range."),
         )),
+        (_, Some(lon)) if !(-180.0..=180.0).contains(&lon) => Err(
+            InternalError::InvalidTrackPoint(format!("{tag} lon {lon} is out of range.")),
+        ),
+        (Some(lat), Some(lon)) => Ok((lat, lon)),
+        _ => Err(InternalError::InvalidTrackPoint(format!(
+            "{tag} missing lat or lon."
+        ))),
     }
 }
 
@@ -225,6 +939,167 @@ fn parse_multiple_trkseg() {
     assert_eq!(down, 5.0);
 }
 
+#[test]
+fn parse_merges_short_segments() {
+    let gpx = r#"
+    <gpx>
+      <trk>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.0"><ele>100</ele></trkpt>
+          <trkpt lat="0.0" lon="0.001"><ele>110</ele></trkpt>
+        </trkseg>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.002"><ele>105</ele></trkpt>
+        </trkseg>
+      </trk>
+    </gpx>
+    "#;
+
+    let opts = ParseOptions {
+        min_segment_points: 2,
+        ..Default::default()
+    };
+    let track = parse_track_with_options(std::io::Cursor::new(gpx), opts).unwrap();
+
+    assert_eq!(track.segment_count(), 1);
+    assert_eq!(track.segments()[0].points().len(), 3);
+
+    let track = parse_track(std::io::Cursor::new(gpx)).unwrap();
+    assert_eq!(track.segment_count(), 2);
+}
+
+#[test]
+fn parse_rejects_out_of_range_coordinates() {
+    let gpx = r#"<gpx><trk><trkseg><trkpt lat="200" lon="0"></trkpt></trkseg></trk></gpx>"#;
+    let err = parse_track(std::io::Cursor::new(gpx)).unwrap_err();
+    assert!(matches!(err, Error::InvalidData { ref message } if message.contains("200")));
+
+    let gpx = r#"<gpx><trk><trkseg><trkpt lat="0" lon="-999"></trkpt></trkseg></trk></gpx>"#;
+    assert!(parse_track(std::io::Cursor::new(gpx)).is_err());
+
+    let gpx = r#"<gpx><trk><trkseg><trkpt lat="90.0" lon="180"></trkpt></trkseg></trk></gpx>"#;
+    let track = parse_track(std::io::Cursor::new(gpx)).unwrap();
+    assert_eq!(track.segments()[0].points()[0].lat, 90.0);
+}
+
+#[test]
+fn parse_heart_rate_extension() {
+    let gpx = r#"
+    <gpx xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+      <trk>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.0">
+            <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>140</gpxtpx:hr></gpxtpx:TrackPointExtension></extensions>
+          </trkpt>
+          <trkpt lat="0.0" lon="0.001">
+            <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>150</gpxtpx:hr></gpxtpx:TrackPointExtension></extensions>
+          </trkpt>
+          <trkpt lat="0.0" lon="0.002"><ele>10</ele></trkpt>
+        </trkseg>
+      </trk>
+    </gpx>
+    "#;
+
+    let track = parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let seg = &track.segments()[0];
+
+    assert_eq!(seg.points()[0].hr, Some(140));
+    assert_eq!(seg.points()[1].hr, Some(150));
+    assert_eq!(seg.points()[2].hr, None);
+    assert_eq!(seg.average_hr(), Some(145.0));
+}
+
+#[test]
+fn parse_cadence_and_power_extensions() {
+    let gpx = r#"
+    <gpx>
+      <trk>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.0">
+            <extensions>
+              <power>200</power>
+              <gpxtpx:TrackPointExtension><gpxtpx:cad>85</gpxtpx:cad></gpxtpx:TrackPointExtension>
+            </extensions>
+          </trkpt>
+          <trkpt lat="0.0" lon="0.001">
+            <extensions><pwr:PowerInWatts>300</pwr:PowerInWatts></extensions>
+          </trkpt>
+          <trkpt lat="0.0" lon="0.002"></trkpt>
+          <trkpt lat="0.0" lon="0.003">
+            <extensions>
+              <gpxtpx:TrackPointExtension><gpxtpx:watts>250</gpxtpx:watts></gpxtpx:TrackPointExtension>
+            </extensions>
+          </trkpt>
+        </trkseg>
+      </trk>
+    </gpx>
+    "#;
+
+    let track = parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let seg = &track.segments()[0];
+
+    assert_eq!(seg.points()[0].cadence, Some(85));
+    assert_eq!(seg.points()[0].power, Some(200));
+    assert_eq!(seg.points()[1].power, Some(300));
+    assert_eq!(seg.points()[2].power, None);
+    assert_eq!(seg.points()[3].power, Some(250));
+    assert_eq!(seg.average_power(), Some(250.0));
+}
+
+#[test]
+fn parse_invalid_utf8_attribute_names_field() {
+    let mut gpx = b"<gpx><trk><trkseg><trkpt lat=\"0.0\" lon=\"".to_vec();
+    gpx.extend_from_slice(&[0xff, 0xfe]);
+    gpx.extend_from_slice(b"\"></trkpt></trkseg></trk></gpx>");
+
+    let err = parse_track(std::io::Cursor::new(gpx)).unwrap_err();
+
+    let Error::InvalidData { message: msg } = err else {
+        panic!("expected InvalidData, got {err:?}");
+    };
+    assert!(msg.starts_with("lon is not valid utf8"), "{msg}");
+    assert!(msg.contains("ff fe"), "{msg}");
+}
+
+#[test]
+fn parse_elevation_priority() {
+    let gpx = r#"
+    <gpx>
+      <trk>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.0">
+            <ele>100</ele>
+            <extensions><baro:ele>97.5</baro:ele></extensions>
+          </trkpt>
+          <trkpt lat="0.0" lon="0.001">
+            <extensions><baro:altitude>98</baro:altitude></extensions>
+          </trkpt>
+          <trkpt lat="0.0" lon="0.002"><ele>101</ele></trkpt>
+        </trkseg>
+      </trk>
+    </gpx>
+    "#;
+
+    let track = parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let eles: Vec<_> = track.points().map(|p| p.ele).collect();
+    assert_eq!(eles, [Some(100.0), Some(98.0), Some(101.0)]);
+
+    let opts = ParseOptions {
+        elevation_priority: vec![ElevationSource::Extension, ElevationSource::Gpx],
+        ..Default::default()
+    };
+    let track = parse_track_with_options(std::io::Cursor::new(gpx), opts).unwrap();
+    let eles: Vec<_> = track.points().map(|p| p.ele).collect();
+    assert_eq!(eles, [Some(97.5), Some(98.0), Some(101.0)]);
+
+    let opts = ParseOptions {
+        elevation_priority: vec![ElevationSource::Gpx],
+        ..Default::default()
+    };
+    let track = parse_track_with_options(std::io::Cursor::new(gpx), opts).unwrap();
+    assert_eq!(track.points().nth(1).unwrap().ele, None);
+}
+
 #[test]
 fn parse_single_trkpt() {
     let gpx = r#"
@@ -249,4 +1124,394 @@ fn parse_single_trkpt() {
     assert_eq!(points[0].lon, 2.0);
     assert_eq!(points[0].time.as_deref(), Some("2024-01-01T00:00:00Z"));
     assert_eq!(points[0].ele, Some(123.45));
+    assert_eq!(points[0].hr, None);
+}
+
+#[cfg(feature = "flate2")]
+#[test]
+fn parse_gz_matches_plain() {
+    use std::io::Write;
+
+    let gpx = r#"
+    <gpx>
+      <trk>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.0"><ele>100</ele></trkpt>
+          <trkpt lat="0.0" lon="0.001"><ele>110</ele></trkpt>
+        </trkseg>
+      </trk>
+    </gpx>
+    "#;
+
+    let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    enc.write_all(gpx.as_bytes()).unwrap();
+    let gz = enc.finish().unwrap();
+
+    let plain = parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let unzipped = parse_track_gz(std::io::Cursor::new(gz)).unwrap();
+    assert_eq!(
+        plain.segments()[0].points(),
+        unzipped.segments()[0].points()
+    );
+
+    let err = parse_track_gz(std::io::Cursor::new(b"not gzip at all".to_vec())).unwrap_err();
+    assert!(matches!(err, Error::Input(_)));
+}
+
+#[test]
+fn track_point_stream_matches_parse_track() {
+    let open =
+        || std::io::BufReader::new(std::fs::File::open("assert/Alt_Portsmouth.gpx").unwrap());
+
+    let streamed: Vec<TrackPoint> = TrackPointStream::new(open())
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    let track = parse_track(open()).unwrap();
+    assert_eq!(streamed.len(), 1219);
+    assert!(streamed.iter().eq(track.points()));
+    assert_eq!(streamed, parse_track_points(open()).unwrap());
+}
+
+#[test]
+fn track_point_stream_reports_segment_index() {
+    let gpx = r#"<gpx><trk>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.0"></trkpt>
+          <trkpt lat="0.0" lon="0.001"></trkpt>
+        </trkseg>
+        <trkseg>
+          <trkpt lat="0.0" lon="0.002"></trkpt>
+        </trkseg>
+    </trk></gpx>"#;
+
+    let mut stream = TrackPointStream::new(std::io::Cursor::new(gpx));
+    assert_eq!(stream.current_segment_index(), None);
+
+    let mut seen = Vec::new();
+    while let Some(pt) = stream.next() {
+        seen.push((stream.current_segment_index().unwrap(), pt.unwrap().lon));
+    }
+
+    assert_eq!(seen, [(0, 0.0), (0, 0.001), (1, 0.002)]);
+}
+
+#[test]
+fn track_point_stream_stops_after_error() {
+    let gpx = r#"<gpx><trk><trkseg>
+        <trkpt lat="0.0" lon="0.0"></trkpt>
+        <trkpt lat="200.0" lon="0.0"></trkpt>
+        <trkpt lat="0.0" lon="0.001"></trkpt>
+    </trkseg></trk></gpx>"#;
+
+    let mut stream = TrackPointStream::new(std::io::Cursor::new(gpx));
+
+    assert!(stream.next().unwrap().is_ok());
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn parse_gps_quality_fields() {
+    let gpx = r#"<gpx><trk><trkseg>
+        <trkpt lat="0.0" lon="0.0">
+          <ele>12</ele><geoidheight>47.3</geoidheight>
+          <fix>3d</fix><sat>9</sat><hdop>0.8</hdop><vdop>1.2</vdop><pdop>1.5</pdop>
+        </trkpt>
+        <trkpt lat="0.0" lon="0.001"></trkpt>
+    </trkseg></trk></gpx>"#;
+
+    let pts = parse_track_points(std::io::Cursor::new(gpx)).unwrap();
+
+    assert_eq!(pts[0].geoidheight, Some(47.3));
+    assert_eq!(pts[0].fix.as_deref(), Some("3d"));
+    assert_eq!(pts[0].sat, Some(9));
+    assert_eq!(pts[0].hdop, Some(0.8));
+    assert_eq!(pts[0].vdop, Some(1.2));
+    assert_eq!(pts[0].pdop, Some(1.5));
+    assert_eq!(
+        pts[1],
+        TrackPoint {
+            lon: 0.001,
+            ..Default::default()
+        }
+    );
+
+    let bad = r#"<gpx><trk><trkseg><trkpt lat="0.0" lon="0.0"><sat>many</sat></trkpt></trkseg></trk></gpx>"#;
+    let err = parse_track(std::io::Cursor::new(bad)).unwrap_err();
+    assert!(matches!(err, Error::InvalidData { ref message } if message.contains("sat")));
+}
+
+#[test]
+fn builder_with_only_coordinates() {
+    let pt = TrackPoint::builder(50.8, -1.28).build();
+
+    assert_eq!((pt.lat, pt.lon), (50.8, -1.28));
+    assert_eq!(
+        pt,
+        TrackPoint {
+            lat: 50.8,
+            lon: -1.28,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn builder_with_elevation_and_time() {
+    let pt = TrackPoint::builder(50.8, -1.28)
+        .ele(46.2)
+        .time("2024-01-01T00:00:00Z")
+        .build();
+
+    assert_eq!(pt.ele, Some(46.2));
+    assert!(pt.timestamp().is_some());
+    assert_eq!(pt.hr, None);
+}
+
+#[test]
+fn display_omits_absent_fields() {
+    let full = TrackPoint::builder(48.1234, 11.5678)
+        .ele(530.2)
+        .time("2024-06-01T10:00:00Z")
+        .hr(142)
+        .cadence(88)
+        .power(210)
+        .build();
+    assert_eq!(
+        full.to_string(),
+        "(lat=48.1234, lon=11.5678, ele=530.2m, time=2024-06-01T10:00:00Z, hr=142bpm, cad=88rpm, power=210W)"
+    );
+
+    let flat = TrackPoint::builder(48.1234, 11.5678)
+        .time("2024-06-01T10:00:00Z")
+        .build();
+    let text = flat.to_string();
+    assert_eq!(
+        text,
+        "(lat=48.1234, lon=11.5678, time=2024-06-01T10:00:00Z)"
+    );
+    assert!(!text.contains("ele="));
+}
+
+#[test]
+fn parse_skips_bom_and_declaration() {
+    let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="Windows tool"><trk><trkseg>
+  <trkpt lat="0.0" lon="0.0"><ele>1</ele></trkpt>
+  <trkpt lat="0.0" lon="0.001"><ele>2</ele></trkpt>
+</trkseg></trk></gpx>"#;
+    let mut with_bom = UTF8_BOM.to_vec();
+    with_bom.extend_from_slice(body.as_bytes());
+
+    let plain = parse_track(std::io::Cursor::new(body)).unwrap();
+    let track = parse_track(std::io::Cursor::new(&with_bom)).unwrap();
+
+    assert_eq!(track, plain);
+    assert_eq!(track.total_point_count(), 2);
+    assert_eq!(track.creator(), Some("Windows tool"));
+    assert_eq!(
+        parse_track_points(std::io::Cursor::new(&with_bom)).unwrap(),
+        parse_track_points(std::io::Cursor::new(body)).unwrap()
+    );
+}
+
+#[test]
+fn parse_gpx_1_0_matches_1_1() {
+    let v10 = Track::from_path("assert/Portsea_GPX10.gpx").unwrap();
+    let v11 = Track::from_path("assert/Portsea_GPX11.gpx").unwrap();
+
+    assert_eq!(v10.gpx_version(), Some("1.0"));
+    assert_eq!(v11.gpx_version(), Some("1.1"));
+    assert_eq!(v10.total_point_count(), 7);
+    assert_eq!(v10.segments, v11.segments);
+    assert_eq!(v10.total_distance_m(), v11.total_distance_m());
+    assert_eq!(v10.total_ascent_descent_m(), v11.total_ascent_descent_m());
+}
+
+#[test]
+fn parse_track_strict_rejects_waypoint_only_file() {
+    let gpx = r#"<gpx version="1.1"><wpt lat="1.0" lon="2.0"><name>Cafe</name></wpt></gpx>"#;
+
+    let lenient = parse_track(std::io::Cursor::new(gpx)).unwrap();
+    assert_eq!(lenient.total_point_count(), 0);
+    assert_eq!(lenient.waypoints().len(), 1);
+
+    let err = parse_track_strict(std::io::Cursor::new(gpx)).unwrap_err();
+    assert!(matches!(err, Error::NoTrackData));
+
+    let html = "<html><body><p>not a track</p></body></html>";
+    assert!(matches!(
+        parse_track_strict(std::io::Cursor::new(html)),
+        Err(Error::NoTrackData)
+    ));
+    assert!(
+        parse_track_strict(std::io::Cursor::new(
+            r#"<gpx><trk><trkseg><trkpt lat="0" lon="0"></trkpt></trkseg></trk></gpx>"#
+        ))
+        .is_ok()
+    );
+}
+
+#[test]
+fn parse_track_str_matches_cursor() {
+    let gpx = r#"<gpx><wpt lat="1.0" lon="2.0"></wpt><trk><trkseg>
+        <trkpt lat="0.0" lon="0.0"><ele>1</ele></trkpt>
+        <trkpt lat="0.0" lon="0.001"><ele>2</ele></trkpt>
+    </trkseg></trk></gpx>"#;
+
+    let expected = parse_track(std::io::Cursor::new(gpx)).unwrap();
+
+    assert_eq!(parse_track_str(gpx).unwrap(), expected);
+    assert_eq!(parse_track_bytes(gpx.as_bytes()).unwrap(), expected);
+    assert_eq!(gpx.parse::<Track>().unwrap(), expected);
+    assert!("<gpx><trk></gpx>".parse::<Track>().is_err());
+}
+
+#[test]
+fn parse_options_skip_invalid_points() {
+    let gpx = r#"<gpx><trk><trkseg>
+        <trkpt lat="1.0" lon="1.0"><ele>10</ele></trkpt>
+        <trkpt lat="north" lon="1.1"><ele>11</ele></trkpt>
+        <trkpt lat="1.2" lon="1.2"><ele>twelve</ele></trkpt>
+        <trkpt lat="1.3" lon="1.3"></trkpt>
+        <trkpt lat="1.4" lon="1.4"><ele>14</ele></trkpt>
+    </trkseg></trk></gpx>"#;
+    let parse = |opts| parse_track_with_options(std::io::Cursor::new(gpx), opts);
+
+    let err = parse(ParseOptions::default()).unwrap_err();
+    assert!(matches!(err, Error::InvalidData { .. }), "{err:?}");
+
+    let track = parse(ParseOptions {
+        skip_invalid_points: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let lats: Vec<f64> = track.points().map(|p| p.lat).collect();
+    assert_eq!(lats, [1.0, 1.3, 1.4]);
+
+    let track = parse(ParseOptions {
+        skip_invalid_points: true,
+        strict_elevation: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let lats: Vec<f64> = track.points().map(|p| p.lat).collect();
+    assert_eq!(lats, [1.0, 1.4]);
+}
+
+#[test]
+fn parse_options_max_points() {
+    let gpx = r#"<gpx><trk>
+        <trkseg><trkpt lat="1.0" lon="1.0"></trkpt><trkpt lat="1.1" lon="1.1"></trkpt></trkseg>
+        <trkseg><trkpt lat="1.2" lon="1.2"></trkpt></trkseg>
+    </trk></gpx>"#;
+    let parse = |max_points| {
+        parse_track_with_options(
+            std::io::Cursor::new(gpx),
+            ParseOptions {
+                max_points,
+                ..Default::default()
+            },
+        )
+    };
+
+    assert_eq!(parse(Some(3)).unwrap().total_point_count(), 3);
+    assert!(matches!(parse(Some(2)), Err(Error::InvalidData { .. })));
+}
+
+#[test]
+fn parse_namespace_prefixed_elements() {
+    let gpx = r#"<?xml version="1.0"?>
+    <gpx:gpx xmlns:gpx="http://www.topografix.com/GPX/1/1" creator="ns0 exporter" version="1.1">
+      <gpx:wpt lat="46.537" lon="7.962"><gpx:name>Jungfrau</gpx:name></gpx:wpt>
+      <gpx:trk><gpx:trkseg>
+        <gpx:trkpt lat="46.5" lon="7.9">
+          <gpx:ele>1200.5</gpx:ele>
+          <gpx:time>2024-01-01T10:00:00Z</gpx:time>
+          <gpx:extensions><ns3:TrackPointExtension><ns3:hr>140</ns3:hr></ns3:TrackPointExtension></gpx:extensions>
+        </gpx:trkpt>
+      </gpx:trkseg><gpx:trkseg>
+        <gpx:trkpt lat="46.6" lon="8.0"><ns0:ele>1300</ns0:ele></gpx:trkpt>
+      </gpx:trkseg></gpx:trk>
+    </gpx:gpx>"#;
+
+    let track = parse_track_str(gpx).unwrap();
+    let streamed: Vec<TrackPoint> = TrackPointStream::new(gpx.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(track.creator(), Some("ns0 exporter"));
+    assert_eq!(track.segment_count(), 2);
+    let pt = &track[0][0];
+    assert_eq!(pt.ele, Some(1200.5));
+    assert_eq!(pt.time.as_deref(), Some("2024-01-01T10:00:00Z"));
+    assert_eq!(pt.hr, Some(140));
+    assert_eq!(track[1][0].ele, Some(1300.0));
+    assert_eq!(track.waypoints()[0].name.as_deref(), Some("Jungfrau"));
+    assert_eq!(streamed, track.points().cloned().collect::<Vec<_>>());
+}
+
+#[test]
+fn parse_children_in_any_order() {
+    let track = Track::from_path("assert/Element_Order.gpx").unwrap();
+
+    let got: Vec<(Option<f64>, Option<&str>)> =
+        track.points().map(|p| (p.ele, p.time.as_deref())).collect();
+    assert_eq!(
+        got,
+        [
+            (Some(8.6), Some("2024-05-04T09:00:00Z")),
+            (Some(9.4), Some("2024-05-04T09:01:00Z")),
+            (Some(11.0), Some("2024-05-04T09:02:00Z")),
+        ]
+    );
+    assert_eq!(track[0][2].hr, Some(120));
+    assert_eq!(track[0][2].sat, Some(8));
+
+    let streamed: Vec<TrackPoint> = TrackPointStream::new(std::io::BufReader::new(
+        std::fs::File::open("assert/Element_Order.gpx").unwrap(),
+    ))
+    .collect::<Result<_, _>>()
+    .unwrap();
+    assert_eq!(streamed, track.points().cloned().collect::<Vec<_>>());
+}
+
+#[test]
+fn parse_track_lenient_collects_point_errors() {
+    let gpx = r#"<gpx><trk><trkseg>
+        <trkpt lat="1.0" lon="1.0"><ele>10</ele></trkpt>
+        <trkpt lat="1.1" lon="1.1"><ele>high</ele></trkpt>
+    </trkseg></trk></gpx>"#;
+
+    let (track, errors) = parse_track_lenient(std::io::Cursor::new(gpx));
+
+    assert_eq!(track.total_point_count(), 1);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "invalid GPX data: ele is not a number"
+    );
+    assert!(parse_track(std::io::Cursor::new(gpx)).is_err());
+
+    let (track, errors) = parse_track_lenient(std::io::Cursor::new(
+        r#"<gpx><trk><trkseg><trkpt lat="1.0" lon="1.0"></trkpt></trkseg><trkseg></trk></gpx>"#,
+    ));
+    assert_eq!(track.total_point_count(), 1);
+    assert!(matches!(errors[..], [Error::InvalidFormat { .. }]));
+}
+
+#[test]
+fn speed_to_over_one_second() {
+    let lon = (111.0 / crate::gpx::geo::EARTH_RADIUS_M).to_degrees();
+    let a = TrackPoint::builder(0.0, 0.0)
+        .time("2024-01-01T10:00:00Z")
+        .build();
+    let b = TrackPoint::builder(0.0, lon)
+        .time("2024-01-01T10:00:01Z")
+        .build();
+
+    assert!((a.speed_to(&b).unwrap() - 399.6).abs() < 1e-9);
+    assert_eq!(a.speed_to(&a), None);
+    assert_eq!(TrackPoint::builder(0.0, 0.0).build().speed_to(&b), None);
 }
@@ -0,0 +1,186 @@
+use crate::gpx::{
+    err::InternalError,
+    trkpt::{TextHandler, parse_number},
+};
+
+/// A `<wpt>` point of interest.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Waypoint {
+    pub lat: f64,
+    pub lon: f64,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub ele: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub time: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub desc: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub sym: Option<WaypointKind>,
+    /// The `<type>` classification of the waypoint.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub classification: Option<String>,
+}
+
+/// Typed form of the common GPX `<sym>` values. Symbols without a dedicated
+/// variant are kept verbatim in [`WaypointKind::Other`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WaypointKind {
+    Summit,
+    Parking,
+    WaterSource,
+    Campground,
+    Restroom,
+    TrailHead,
+    Lodging,
+    Restaurant,
+    Other(String),
+}
+
+impl WaypointKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            WaypointKind::Summit => "Summit",
+            WaypointKind::Parking => "Parking Area",
+            WaypointKind::WaterSource => "Water Source",
+            WaypointKind::Campground => "Campground",
+            WaypointKind::Restroom => "Restroom",
+            WaypointKind::TrailHead => "Trail Head",
+            WaypointKind::Lodging => "Lodging",
+            WaypointKind::Restaurant => "Restaurant",
+            WaypointKind::Other(s) => s,
+        }
+    }
+}
+
+impl From<&str> for WaypointKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "Summit" => WaypointKind::Summit,
+            "Parking" | "Parking Area" => WaypointKind::Parking,
+            "Water Source" | "Drinking Water" => WaypointKind::WaterSource,
+            "Campground" => WaypointKind::Campground,
+            "Restroom" => WaypointKind::Restroom,
+            "Trail Head" | "Trailhead" => WaypointKind::TrailHead,
+            "Lodging" => WaypointKind::Lodging,
+            "Restaurant" => WaypointKind::Restaurant,
+            other => WaypointKind::Other(other.to_string()),
+        }
+    }
+}
+
+pub(crate) type WptApplyfn = fn(&mut Waypoint, &str) -> Result<(), InternalError>;
+
+fn apply_ele(wpt: &mut Waypoint, s: &str) -> Result<(), InternalError> {
+    wpt.ele = Some(parse_number(s, "ele")?);
+    Ok(())
+}
+
+fn apply_time(wpt: &mut Waypoint, s: &str) -> Result<(), InternalError> {
+    wpt.time = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_name(wpt: &mut Waypoint, s: &str) -> Result<(), InternalError> {
+    wpt.name = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_desc(wpt: &mut Waypoint, s: &str) -> Result<(), InternalError> {
+    wpt.desc = Some(s.to_string());
+    Ok(())
+}
+
+fn apply_sym(wpt: &mut Waypoint, s: &str) -> Result<(), InternalError> {
+    wpt.sym = Some(WaypointKind::from(s));
+    Ok(())
+}
+
+fn apply_type(wpt: &mut Waypoint, s: &str) -> Result<(), InternalError> {
+    wpt.classification = Some(s.to_string());
+    Ok(())
+}
+
+const HANDLERS: &[TextHandler<Waypoint>] = &[
+    TextHandler {
+        tag: b"ele",
+        apply: apply_ele,
+    },
+    TextHandler {
+        tag: b"time",
+        apply: apply_time,
+    },
+    TextHandler {
+        tag: b"name",
+        apply: apply_name,
+    },
+    TextHandler {
+        tag: b"desc",
+        apply: apply_desc,
+    },
+    TextHandler {
+        tag: b"sym",
+        apply: apply_sym,
+    },
+    TextHandler {
+        tag: b"type",
+        apply: apply_type,
+    },
+];
+
+pub(crate) fn find_handler(tag: &[u8]) -> Option<WptApplyfn> {
+    HANDLERS.iter().find(|h| h.tag == tag).map(|h| h.apply)
+}
+
+#[test]
+fn parse_waypoint_sym_and_type() {
+    let gpx = r#"
+    <gpx>
+      <wpt lat="46.5" lon="7.9">
+        <ele>4158</ele>
+        <name>Jungfrau</name>
+        <sym>Summit</sym>
+        <type>Peak</type>
+      </wpt>
+      <wpt lat="46.6" lon="7.9">
+        <sym>Bell</sym>
+      </wpt>
+    </gpx>
+    "#;
+
+    let track = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let wpts = track.waypoints();
+
+    assert_eq!(wpts.len(), 2);
+    assert_eq!(wpts[0].sym, Some(WaypointKind::Summit));
+    assert_eq!(wpts[0].name.as_deref(), Some("Jungfrau"));
+    assert_eq!(wpts[0].classification.as_deref(), Some("Peak"));
+    assert_eq!(wpts[0].ele, Some(4158.0));
+    assert_eq!(wpts[1].sym, Some(WaypointKind::Other("Bell".into())));
+}
+
+#[test]
+fn parse_self_closing_waypoint() {
+    let gpx = r#"
+    <gpx>
+      <wpt lat="46.5" lon="7.9"/>
+      <wpt lat="46.6" lon="8.0"><name>Eiger</name></wpt>
+      <wpt lat="46.7" lon="8.1" />
+    </gpx>
+    "#;
+
+    let track = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+    let wpts = track.waypoints();
+
+    assert_eq!(wpts.len(), 3);
+    assert_eq!((wpts[0].lat, wpts[0].lon), (46.5, 7.9));
+    assert_eq!(wpts[0].name, None);
+    assert_eq!(wpts[1].name.as_deref(), Some("Eiger"));
+    assert_eq!((wpts[2].lat, wpts[2].lon), (46.7, 8.1));
+
+    let err = crate::gpx::parse_track(std::io::Cursor::new(r#"<gpx><wpt lat="46.5"/></gpx>"#));
+    assert!(err.is_err());
+}
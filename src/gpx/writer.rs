@@ -0,0 +1,236 @@
+use std::io::Write;
+
+use quick_xml::{
+    Writer,
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+};
+
+use crate::gpx::{Error, Metadata, Track, TrackPoint, Waypoint, err::InternalError};
+
+const GPX_NS: &str = "http://www.topografix.com/GPX/1/1";
+const TPX_NS: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v1";
+const PWR_NS: &str = "http://www.garmin.com/xmlschemas/PowerExtension/v1";
+const GPXX_NS: &str = "http://www.garmin.com/xmlschemas/GpxExtensions/v3";
+
+/// Serializes `track` as a GPX 1.1 document. Optional fields that are `None`
+/// are omitted.
+pub fn write_track<W: Write>(track: &Track, writer: W) -> Result<(), Error> {
+    write_gpx(track, &mut Writer::new_with_indent(writer, b' ', 2)).map_err(Error::from)
+}
+
+fn write_gpx<W: Write>(track: &Track, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    xml.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    let mut gpx = BytesStart::new("gpx");
+    gpx.push_attribute(("version", "1.1"));
+    gpx.push_attribute(("creator", track.creator().unwrap_or("rgpxsee")));
+    gpx.push_attribute(("xmlns", GPX_NS));
+    gpx.push_attribute(("xmlns:gpxtpx", TPX_NS));
+    gpx.push_attribute(("xmlns:pwr", PWR_NS));
+    gpx.push_attribute(("xmlns:gpxx", GPXX_NS));
+    xml.write_event(Event::Start(gpx))?;
+
+    if let Some(meta) = track.metadata() {
+        write_metadata(meta, xml)?;
+    }
+    for wpt in track.waypoints() {
+        write_wpt(wpt, xml)?;
+    }
+
+    xml.write_event(Event::Start(BytesStart::new("trk")))?;
+    if let Some(color) = track.display_color() {
+        xml.write_event(Event::Start(BytesStart::new("extensions")))?;
+        xml.write_event(Event::Start(BytesStart::new("gpxx:TrackExtension")))?;
+        write_text(xml, "gpxx:DisplayColor", color)?;
+        xml.write_event(Event::End(BytesEnd::new("gpxx:TrackExtension")))?;
+        xml.write_event(Event::End(BytesEnd::new("extensions")))?;
+    }
+    for seg in track.segments() {
+        xml.write_event(Event::Start(BytesStart::new("trkseg")))?;
+        for pt in seg.points() {
+            write_trkpt(pt, xml)?;
+        }
+        xml.write_event(Event::End(BytesEnd::new("trkseg")))?;
+    }
+    xml.write_event(Event::End(BytesEnd::new("trk")))?;
+
+    xml.write_event(Event::End(BytesEnd::new("gpx")))?;
+    xml.get_mut().flush()?;
+    Ok(())
+}
+
+fn write_metadata<W: Write>(meta: &Metadata, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    xml.write_event(Event::Start(BytesStart::new("metadata")))?;
+
+    if let Some(name) = &meta.name {
+        write_text(xml, "name", name)?;
+    }
+    if let Some(desc) = &meta.desc {
+        write_text(xml, "desc", desc)?;
+    }
+    if let Some(author) = &meta.author {
+        xml.write_event(Event::Start(BytesStart::new("author")))?;
+        write_text(xml, "name", author)?;
+        xml.write_event(Event::End(BytesEnd::new("author")))?;
+    }
+    if let Some(link) = &meta.link {
+        let mut start = BytesStart::new("link");
+        start.push_attribute(("href", link.as_str()));
+        xml.write_event(Event::Empty(start))?;
+    }
+    if let Some(time) = &meta.time {
+        write_text(xml, "time", time)?;
+    }
+
+    xml.write_event(Event::End(BytesEnd::new("metadata")))?;
+    Ok(())
+}
+
+fn write_trkpt<W: Write>(pt: &TrackPoint, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    xml.write_event(Event::Start(lat_lon_start("trkpt", pt.lat, pt.lon)))?;
+
+    if let Some(ele) = pt.ele {
+        write_text(xml, "ele", &ele.to_string())?;
+    }
+    if let Some(time) = &pt.time {
+        write_text(xml, "time", time)?;
+    }
+    if let Some(geoidheight) = pt.geoidheight {
+        write_text(xml, "geoidheight", &geoidheight.to_string())?;
+    }
+    if let Some(fix) = &pt.fix {
+        write_text(xml, "fix", fix)?;
+    }
+    if let Some(sat) = pt.sat {
+        write_text(xml, "sat", &sat.to_string())?;
+    }
+    if let Some(hdop) = pt.hdop {
+        write_text(xml, "hdop", &hdop.to_string())?;
+    }
+    if let Some(vdop) = pt.vdop {
+        write_text(xml, "vdop", &vdop.to_string())?;
+    }
+    if let Some(pdop) = pt.pdop {
+        write_text(xml, "pdop", &pdop.to_string())?;
+    }
+    if pt.hr.is_some() || pt.cadence.is_some() || pt.power.is_some() {
+        xml.write_event(Event::Start(BytesStart::new("extensions")))?;
+        if let Some(power) = pt.power {
+            write_text(xml, "pwr:PowerInWatts", &power.to_string())?;
+        }
+        if pt.hr.is_some() || pt.cadence.is_some() {
+            xml.write_event(Event::Start(BytesStart::new("gpxtpx:TrackPointExtension")))?;
+            if let Some(hr) = pt.hr {
+                write_text(xml, "gpxtpx:hr", &hr.to_string())?;
+            }
+            if let Some(cad) = pt.cadence {
+                write_text(xml, "gpxtpx:cad", &cad.to_string())?;
+            }
+            xml.write_event(Event::End(BytesEnd::new("gpxtpx:TrackPointExtension")))?;
+        }
+        xml.write_event(Event::End(BytesEnd::new("extensions")))?;
+    }
+
+    xml.write_event(Event::End(BytesEnd::new("trkpt")))?;
+    Ok(())
+}
+
+fn write_wpt<W: Write>(wpt: &Waypoint, xml: &mut Writer<W>) -> Result<(), InternalError> {
+    xml.write_event(Event::Start(lat_lon_start("wpt", wpt.lat, wpt.lon)))?;
+
+    if let Some(ele) = wpt.ele {
+        write_text(xml, "ele", &ele.to_string())?;
+    }
+    if let Some(time) = &wpt.time {
+        write_text(xml, "time", time)?;
+    }
+    if let Some(name) = &wpt.name {
+        write_text(xml, "name", name)?;
+    }
+    if let Some(desc) = &wpt.desc {
+        write_text(xml, "desc", desc)?;
+    }
+    if let Some(sym) = &wpt.sym {
+        write_text(xml, "sym", sym.as_str())?;
+    }
+    if let Some(classification) = &wpt.classification {
+        write_text(xml, "type", classification)?;
+    }
+
+    xml.write_event(Event::End(BytesEnd::new("wpt")))?;
+    Ok(())
+}
+
+fn lat_lon_start(tag: &str, lat: f64, lon: f64) -> BytesStart<'_> {
+    let mut start = BytesStart::new(tag);
+    start.push_attribute(("lat", lat.to_string().as_str()));
+    start.push_attribute(("lon", lon.to_string().as_str()));
+    start
+}
+
+pub(crate) fn write_text<W: Write>(
+    xml: &mut Writer<W>,
+    tag: &str,
+    text: &str,
+) -> Result<(), InternalError> {
+    xml.write_event(Event::Start(BytesStart::new(tag)))?;
+    xml.write_event(Event::Text(BytesText::new(text)))?;
+    xml.write_event(Event::End(BytesEnd::new(tag)))?;
+    Ok(())
+}
+
+#[test]
+fn write_track_round_trip() {
+    let gpx = r#"
+    <gpx creator="Garmin Connect">
+      <metadata>
+        <name>Lunch run</name>
+        <desc>Southampton &amp; back</desc>
+        <author><name>Jo Bloggs</name></author>
+        <link href="https://example.com/?run=1&amp;lap=2"/>
+        <time>2024-01-01T00:00:00Z</time>
+      </metadata>
+      <wpt lat="1.5" lon="2.5"><name>Fish &amp; Chips</name><sym>Restaurant</sym></wpt>
+      <trk>
+        <extensions><gpxx:TrackExtension><gpxx:DisplayColor>Red</gpxx:DisplayColor></gpxx:TrackExtension></extensions>
+        <trkseg>
+          <trkpt lat="50.87551" lon="-1.28259"><ele>46.164</ele><time>2024-01-01T00:00:00Z</time></trkpt>
+          <trkpt lat="50.87545" lon="-1.28237"><ele>46.848</ele><fix>3d</fix><sat>7</sat><hdop>1.4</hdop><pdop>2.1</pdop></trkpt>
+        </trkseg>
+        <trkseg>
+          <trkpt lat="50.87533" lon="-1.28199"></trkpt>
+          <trkpt lat="50.87522" lon="-1.28158">
+            <extensions><power>250</power><gpxtpx:TrackPointExtension><gpxtpx:hr>131</gpxtpx:hr><gpxtpx:cad>90</gpxtpx:cad></gpxtpx:TrackPointExtension></extensions>
+          </trkpt>
+        </trkseg>
+      </trk>
+    </gpx>
+    "#;
+    let track = crate::gpx::parse_track(std::io::Cursor::new(gpx)).unwrap();
+
+    let mut out = Vec::new();
+    write_track(&track, &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let back = crate::gpx::parse_track(std::io::Cursor::new(&text)).unwrap();
+
+    assert!(text.contains(r#"version="1.1""#));
+    assert!(!text.contains("<ele></ele>"));
+    assert!(text.contains(&format!(r#"xmlns:pwr="{PWR_NS}""#)));
+    assert!(text.contains("<pwr:PowerInWatts>250</pwr:PowerInWatts>"));
+    assert!(!text.contains("<power>"));
+    assert_eq!(back.segment_count(), 2);
+    assert_eq!(back.total_point_count(), track.total_point_count());
+    assert_eq!(back.total_distance_m(), track.total_distance_m());
+    for (a, b) in back.segments().iter().zip(track.segments()) {
+        assert_eq!(a.points(), b.points());
+    }
+    assert_eq!(back.waypoints(), track.waypoints());
+    assert_eq!(back.creator(), Some("Garmin Connect"));
+    assert_eq!(back.metadata(), track.metadata());
+    assert_eq!(
+        back.metadata().and_then(|m| m.link.as_deref()),
+        Some("https://example.com/?run=1&lap=2")
+    );
+    assert_eq!(back.display_color(), Some("Red"));
+    assert_eq!(back.gpx_version(), Some("1.1"));
+}
@@ -1,34 +1,205 @@
-use std::{env, fs::File, io::BufReader, process};
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process,
+};
 
-use rgpxsee::gpx::{Track, parse_track};
+use rgpxsee::gpx::{Track, kml, tcx, write_track};
+
+const USAGE: &str =
+    "Usage: rgpxsee <file.gpx> [-o|--output <path>] [--format summary|gpx|tcx|csv|kml|geojson]";
 
 fn main() {
-    if let Err(e) = run() {
+    if let Err(e) = run(env::args().skip(1)) {
         eprintln!("Error :{e}");
         process::exit(1);
     }
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let path = env::args().nth(1).ok_or("Usage: rgpxsee <file.gpx>")?;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Summary,
+    Gpx,
+    Tcx,
+    Csv,
+    Kml,
+    #[cfg(feature = "geojson")]
+    GeoJson,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "summary" => Ok(Format::Summary),
+            "gpx" => Ok(Format::Gpx),
+            "tcx" => Ok(Format::Tcx),
+            "csv" => Ok(Format::Csv),
+            "kml" => Ok(Format::Kml),
+            #[cfg(feature = "geojson")]
+            "geojson" => Ok(Format::GeoJson),
+            other => Err(format!("unsupported format: {other}")),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Args {
+    input: String,
+    output: Option<PathBuf>,
+    format: Format,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut format = Format::Summary;
 
-    let file = File::open(&path)?;
-    let reader = BufReader::new(file);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(args.next().ok_or(USAGE)?));
+            }
+            "--format" => {
+                format = args.next().ok_or(USAGE)?.parse()?;
+            }
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(USAGE.into()),
+        }
+    }
 
-    let track: Track = parse_track(reader)?;
+    Ok(Args {
+        input: input.ok_or(USAGE)?,
+        output,
+        format,
+    })
+}
 
-    let distance_km = track.total_distance_m() / 1000.0;
-    let (ascent, descent) = track.total_ascent_descent_m();
+fn run(args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args(args)?;
 
-    // 统计点数
-    let point_count: usize = track.segments().iter().map(|s| s.points().len()).sum();
+    let track = Track::from_path(&args.input)?;
 
-    println!("File: {}", path);
-    println!("Segments: {}", track.segment_count());
-    println!("Points: {}", point_count);
-    println!("Distance: {:.2} km", distance_km);
-    println!("Ascent: {:.1} m", ascent);
-    println!("Descent: {:.1} m", descent);
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    match args.format {
+        Format::Summary => write_summary(&mut out, &args.input, &track)?,
+        Format::Gpx => write_track(&track, &mut out)?,
+        Format::Tcx => tcx::write_tcx(&track, &mut out)?,
+        Format::Csv => track.write_csv(&mut out)?,
+        Format::Kml => kml::write_kml(&track, &mut out)?,
+        #[cfg(feature = "geojson")]
+        Format::GeoJson => {
+            out.write_all(rgpxsee::gpx::geojson::track_to_geojson(&track).as_bytes())?
+        }
+    }
+    out.flush()?;
 
     Ok(())
 }
+
+fn write_summary(out: &mut dyn Write, path: &str, track: &Track) -> io::Result<()> {
+    let stats = track.stats();
+
+    writeln!(out, "File: {}", path)?;
+    writeln!(out, "Segments: {}", stats.segment_count)?;
+    writeln!(out, "Points: {}", stats.point_count)?;
+    writeln!(out, "Distance: {:.2} km", stats.distance_m / 1000.0)?;
+    writeln!(out, "Ascent: {:.1} m", stats.ascent_m)?;
+    writeln!(out, "Descent: {:.1} m", stats.descent_m)?;
+    if let Some(duration) = stats.duration {
+        let secs = duration.as_secs();
+        writeln!(
+            out,
+            "Duration: {}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )?;
+    }
+    if let Some(speed) = stats.avg_speed_kmh {
+        writeln!(out, "Average speed: {:.1} km/h", speed)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn run_writes_summary_to_output_file() {
+    let out = env::temp_dir().join(format!("rgpxsee-summary-{}.txt", process::id()));
+
+    let args = [
+        "assert/Alt_Portsmouth.gpx",
+        "-o",
+        out.to_str().unwrap(),
+        "--format",
+        "summary",
+    ];
+    run(args.iter().map(|s| s.to_string())).unwrap();
+
+    let text = std::fs::read_to_string(&out).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert!(text.contains("Segments: 1"));
+    assert!(text.contains("Points: 1219"));
+}
+
+#[test]
+fn parse_args_rejects_unknown_format() {
+    let args = ["track.gpx", "--format", "shapefile"];
+
+    assert!(parse_args(args.iter().map(|s| s.to_string())).is_err());
+}
+
+#[test]
+fn run_writes_kml_to_output_file() {
+    let out = env::temp_dir().join(format!("rgpxsee-{}.kml", process::id()));
+
+    let args = [
+        "assert/Alt_Portsmouth.gpx",
+        "-o",
+        out.to_str().unwrap(),
+        "--format",
+        "kml",
+    ];
+    run(args.iter().map(|s| s.to_string())).unwrap();
+
+    let text = std::fs::read_to_string(&out).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert_eq!(text.matches("<LineString>").count(), 1);
+    let coords = text.split("<coordinates>").nth(1).unwrap();
+    let coords = coords.split("</coordinates>").next().unwrap();
+    assert_eq!(coords.split(' ').count(), 1219);
+}
+
+#[cfg(feature = "geojson")]
+#[test]
+fn run_writes_geojson_to_output_file() {
+    let out = env::temp_dir().join(format!("rgpxsee-{}.geojson", process::id()));
+
+    let args = [
+        "assert/Alt_Portsmouth.gpx",
+        "--output",
+        out.to_str().unwrap(),
+        "--format",
+        "geojson",
+    ];
+    run(args.iter().map(|s| s.to_string())).unwrap();
+
+    let text = std::fs::read_to_string(&out).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(v["type"], "FeatureCollection");
+    assert_eq!(
+        v["features"][0]["geometry"]["coordinates"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1219
+    );
+}